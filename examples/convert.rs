@@ -0,0 +1,47 @@
+// Copyright Jeron Lau 2017 - 2018.
+// Dual-licensed under either the MIT License or the Boost Software License, Version 1.0.
+// (See accompanying file LICENSE_1_0.txt or copy at https://www.boost.org/LICENSE_1_0.txt)
+
+//! Time converting a 3840×2160 (4K) 8-bit frame between formats.  Run with
+//! `cargo run --release --example convert`.
+
+extern crate afi;
+
+use afi::*;
+use std::time::{Duration, Instant};
+
+/// The fastest of a few runs.
+fn time<F: FnMut() -> VFrame>(mut f: F) -> Duration {
+    (0..5)
+        .map(|_| {
+            let start = Instant::now();
+            let frame = f();
+            let elapsed = start.elapsed();
+            assert!(!frame.data.is_empty());
+            elapsed
+        })
+        .min()
+        .unwrap()
+}
+
+fn main() {
+    let (w, h) = (3840, 2160);
+    let frame = VFrame::new((0..w * h * 4).map(|i| (i * 7 / 3) as u8).collect());
+    let pairs = [
+        (Srgba, Sbgra),
+        (Srgba, Sbgr),
+        (Srgba, Lrgba),
+        (Lrgba, Lbgra),
+        (Srgba, YCbCr),
+        (Srgba, YuvItur),
+        (Srgba, YuvNtsc),
+        (Srgba, YCbCr709Limited),
+        (Srgba, YCbCr2020),
+    ];
+    for &(from, to) in &pairs {
+        let there = time(|| frame.to_format(from, to, Depth::U8));
+        let converted = frame.to_format(from, to, Depth::U8);
+        let back = time(|| converted.to_format(to, from, Depth::U8));
+        println!("{:?} -> {:?}: {:?}, back: {:?}", from, to, there, back);
+    }
+}
@@ -0,0 +1,278 @@
+// Copyright Jeron Lau 2017 - 2018.
+// Dual-licensed under either the MIT License or the Boost Software License, Version 1.0.
+// (See accompanying file LICENSE_1_0.txt or copy at https://www.boost.org/LICENSE_1_0.txt)
+
+/// A speaker position.  The order (and bit in a channel mask) matches WAV's
+/// `WAVEFORMATEXTENSIBLE` channel mask.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum Speaker {
+    /// Front Left (FL)
+    FrontLeft = 0,
+    /// Front Right (FR)
+    FrontRight = 1,
+    /// Front Center (FC)
+    FrontCenter = 2,
+    /// Low Frequency Effects / Subwoofer (LFE)
+    Lfe = 3,
+    /// Back Left (BL)
+    BackLeft = 4,
+    /// Back Right (BR)
+    BackRight = 5,
+    /// Front Left of Center (FLC)
+    FrontLeftCenter = 6,
+    /// Front Right of Center (FRC)
+    FrontRightCenter = 7,
+    /// Back Center (BC)
+    BackCenter = 8,
+    /// Side Left (SL)
+    SideLeft = 9,
+    /// Side Right (SR)
+    SideRight = 10,
+    /// Top Center (TC)
+    TopCenter = 11,
+    /// Top Front Left (TFL)
+    TopFrontLeft = 12,
+    /// Top Front Center (TFC)
+    TopFrontCenter = 13,
+    /// Top Front Right (TFR)
+    TopFrontRight = 14,
+    /// Top Back Left (TBL)
+    TopBackLeft = 15,
+    /// Top Back Center (TBC)
+    TopBackCenter = 16,
+    /// Top Back Right (TBR)
+    TopBackRight = 17,
+}
+
+use self::Speaker::*;
+
+/// Every speaker, in WAV order.
+const SPEAKERS: [Speaker; 18] = [
+    FrontLeft,
+    FrontRight,
+    FrontCenter,
+    Lfe,
+    BackLeft,
+    BackRight,
+    FrontLeftCenter,
+    FrontRightCenter,
+    BackCenter,
+    SideLeft,
+    SideRight,
+    TopCenter,
+    TopFrontLeft,
+    TopFrontCenter,
+    TopFrontRight,
+    TopBackLeft,
+    TopBackCenter,
+    TopBackRight,
+];
+
+impl Speaker {
+    /// Get the bit for this speaker in a WAV channel mask.
+    pub fn mask(self) -> u32 {
+        1 << self as u8
+    }
+}
+
+/// A channel layout: which speakers there are samples for.  Channels are
+/// always stored in WAV order; use `ChannelOrder` to convert from and to
+/// other codecs' orders.  Layouts are equal if they have the same speakers,
+/// so `Custom(0b11) == Stereo`.
+#[derive(Copy, Clone, Debug, Default)]
+pub enum AudioChannels {
+    /// Mono = 1 channel (front center)
+    Mono,
+    /// Stereo = 2 channels (front left, front right)
+    #[default]
+    Stereo,
+    /// Quad = 4 channels (front left, front right, back left, back right)
+    Quad,
+    /// Surround = 5 channels (front left, front right, front center,
+    /// back left, back right)
+    Surround,
+    /// 5.1 Surround = 6 channels (front left, front right, front center,
+    /// LFE, back left, back right)
+    Surround51,
+    /// 7.1 Surround = 8 channels (front left, front right, front center,
+    /// LFE, back left, back right, side left, side right)
+    Surround71,
+    /// Any other layout, as a WAV channel mask (see `Speaker::mask`).  Use
+    /// `AudioChannels::from_mask` to check that it has any speakers.
+    Custom(u32),
+}
+
+use self::AudioChannels::*;
+
+impl PartialEq for AudioChannels {
+    fn eq(&self, other: &AudioChannels) -> bool {
+        self.mask() == other.mask()
+    }
+}
+
+impl Eq for AudioChannels {}
+
+impl AudioChannels {
+    /// Get the layout for a WAV channel mask, or `None` if it doesn't have
+    /// any known speakers.
+    pub fn from_mask(mask: u32) -> Option<AudioChannels> {
+        let mask = Custom(mask).mask();
+        if mask == 0 {
+            return None;
+        }
+        for named in &[Mono, Stereo, Quad, Surround, Surround51, Surround71] {
+            if named.mask() == mask {
+                return Some(*named);
+            }
+        }
+        Some(Custom(mask))
+    }
+
+    /// Get the layout with these speakers (in any order), or `None` if there
+    /// aren't any.
+    pub fn from_speakers(speakers: &[Speaker]) -> Option<AudioChannels> {
+        AudioChannels::from_mask(speakers.iter().fold(0, |m, s| m | s.mask()))
+    }
+
+    /// Get the WAV channel mask for this layout.
+    pub fn mask(self) -> u32 {
+        let s = |s: Speaker| s.mask();
+        match self {
+            Mono => s(FrontCenter),
+            Stereo => s(FrontLeft) | s(FrontRight),
+            Quad => Stereo.mask() | s(BackLeft) | s(BackRight),
+            Surround => Quad.mask() | s(FrontCenter),
+            Surround51 => Surround.mask() | s(Lfe),
+            Surround71 => Surround51.mask() | s(SideLeft) | s(SideRight),
+            Custom(mask) => mask & ((1 << SPEAKERS.len()) - 1),
+        }
+    }
+
+    /// Get the speakers, in the order their channels are stored.
+    pub fn speakers(self) -> Vec<Speaker> {
+        let mask = self.mask();
+        SPEAKERS
+            .iter()
+            .cloned()
+            .filter(|s| mask & s.mask() != 0)
+            .collect()
+    }
+
+    /// Return true if the layout has a channel for a speaker.
+    pub fn has(self, speaker: Speaker) -> bool {
+        self.mask() & speaker.mask() != 0
+    }
+
+    /// Get the channel number of a speaker.
+    pub fn channel(self, speaker: Speaker) -> Option<usize> {
+        if self.has(speaker) {
+            Some((self.mask() & (speaker.mask() - 1)).count_ones() as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Return the number of channels.
+    #[inline(always)]
+    pub fn n_channels(self) -> usize {
+        self.mask().count_ones() as usize
+    }
+}
+
+/// The order that a codec stores channels in.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ChannelOrder {
+    /// WAV (`WAVEFORMATEXTENSIBLE`) order, which is used by `AFrame`.
+    Wav,
+    /// FLAC order (same as WAV).
+    Flac,
+    /// Vorbis / Opus order (FL, FC, FR, SL, SR, BL, BR, LFE).
+    Vorbis,
+    /// AC-3 / Dolby Digital order (FL, FC, FR, SL, SR, LFE).
+    Ac3,
+}
+
+impl ChannelOrder {
+    /// Sort key of a speaker in this order.
+    fn rank(self, speaker: Speaker) -> u8 {
+        match self {
+            ChannelOrder::Wav | ChannelOrder::Flac => speaker as u8,
+            ChannelOrder::Vorbis => match speaker {
+                FrontLeft => 0,
+                FrontCenter => 1,
+                FrontRight => 2,
+                SideLeft => 3,
+                SideRight => 4,
+                BackLeft => 5,
+                BackRight => 6,
+                BackCenter => 7,
+                Lfe => 8,
+                other => 9 + other as u8,
+            },
+            ChannelOrder::Ac3 => match speaker {
+                FrontLeft => 0,
+                FrontLeftCenter => 1,
+                FrontCenter => 2,
+                FrontRightCenter => 3,
+                FrontRight => 4,
+                SideLeft | BackLeft => 5,
+                BackCenter => 6,
+                SideRight | BackRight => 7,
+                Lfe => 8,
+                other => 9 + other as u8,
+            },
+        }
+    }
+
+    /// Get the speakers of a layout, in the order this codec stores them.
+    pub fn speakers(self, channels: AudioChannels) -> Vec<Speaker> {
+        let mut speakers = channels.speakers();
+        speakers.sort_by_key(|s| (self.rank(*s), *s as u8));
+        speakers
+    }
+
+    /// Get the channel numbers in `channels` (WAV order) for each channel
+    /// in this order.
+    pub fn map(self, channels: AudioChannels) -> Vec<usize> {
+        self.speakers(channels)
+            .into_iter()
+            .map(|s| channels.channel(s).unwrap())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn named_layouts() {
+        assert_eq!(Surround51.n_channels(), 6);
+        assert_eq!(Surround71.n_channels(), 8);
+        assert_eq!(AudioChannels::from_mask(0x3F), Some(Surround51));
+        assert_eq!(Surround51.channel(Lfe), Some(3));
+        assert_eq!(
+            AudioChannels::from_speakers(&[FrontCenter, FrontLeft, FrontRight]),
+            Some(Custom(0x7))
+        );
+        assert_eq!(AudioChannels::from_mask(0), None);
+        assert_eq!(AudioChannels::from_mask(1 << 31), None);
+        assert_eq!(AudioChannels::from_speakers(&[]), None);
+        assert_eq!(Custom(0x3), Stereo);
+        assert_ne!(Custom(0x7), Surround);
+    }
+
+    #[test]
+    fn codec_orders() {
+        assert_eq!(
+            ChannelOrder::Ac3.speakers(Surround51),
+            vec![FrontLeft, FrontCenter, FrontRight, BackLeft, BackRight, Lfe]
+        );
+        assert_eq!(ChannelOrder::Vorbis.map(Surround51), vec![0, 2, 1, 4, 5, 3]);
+        assert_eq!(
+            ChannelOrder::Flac.map(Surround71),
+            (0..8).collect::<Vec<_>>()
+        );
+    }
+}
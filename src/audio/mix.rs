@@ -0,0 +1,207 @@
+// Copyright Jeron Lau 2017 - 2018.
+// Dual-licensed under either the MIT License or the Boost Software License, Version 1.0.
+// (See accompanying file LICENSE_1_0.txt or copy at https://www.boost.org/LICENSE_1_0.txt)
+
+use std::f64::consts::FRAC_1_SQRT_2;
+
+use AFrame;
+use Audio;
+use AudioChannels;
+use Samples;
+use Speaker;
+
+/// Where to send a speaker's signal when the output doesn't have it: either
+/// split between a pair of speakers (`true`), or to the first of a list of
+/// alternatives that the output has (`false`).
+fn fallback(speaker: Speaker) -> (bool, &'static [(Speaker, f64)]) {
+    use Speaker::*;
+
+    const H: f64 = FRAC_1_SQRT_2;
+    match speaker {
+        FrontLeft | FrontRight => (false, &[(FrontCenter, H)]),
+        FrontCenter => (true, &[(FrontLeft, H), (FrontRight, H)]),
+        Lfe => (false, &[]),
+        BackLeft => (false, &[(SideLeft, 1.0), (FrontLeft, H)]),
+        BackRight => (false, &[(SideRight, 1.0), (FrontRight, H)]),
+        SideLeft => (false, &[(BackLeft, 1.0), (FrontLeft, H)]),
+        SideRight => (false, &[(BackRight, 1.0), (FrontRight, H)]),
+        FrontLeftCenter => (false, &[(FrontLeft, 1.0), (FrontCenter, 1.0)]),
+        FrontRightCenter => (false, &[(FrontRight, 1.0), (FrontCenter, 1.0)]),
+        BackCenter => (true, &[(BackLeft, H), (BackRight, H)]),
+        TopCenter => (false, &[(FrontCenter, H)]),
+        TopFrontLeft => (false, &[(FrontLeft, 1.0)]),
+        TopFrontCenter => (false, &[(FrontCenter, 1.0)]),
+        TopFrontRight => (false, &[(FrontRight, 1.0)]),
+        TopBackLeft => (false, &[(BackLeft, 1.0)]),
+        TopBackCenter => (false, &[(BackCenter, 1.0)]),
+        TopBackRight => (false, &[(BackRight, 1.0)]),
+    }
+}
+
+/// Send `gain` of a speaker's signal to the output, following fallbacks up to
+/// `depth` times if the output doesn't have the speaker.
+fn fold<F>(speaker: Speaker, gain: f64, depth: u8, to: AudioChannels, add: &mut F)
+where
+    F: FnMut(usize, f64),
+{
+    if let Some(output) = to.channel(speaker) {
+        add(output, gain);
+        return;
+    }
+    if depth == 0 {
+        return;
+    }
+
+    let (split, list) = fallback(speaker);
+    if split {
+        for &(s, g) in list {
+            fold(s, gain * g, depth - 1, to, add);
+        }
+    } else if let Some(&(s, g)) = list.iter().find(|(s, _)| to.has(*s)).or(list.last()) {
+        fold(s, gain * g, depth - 1, to, add);
+    }
+}
+
+/// A matrix of gains for mixing audio from one channel layout to another.
+#[derive(Clone, PartialEq, Debug)]
+pub struct MixMatrix {
+    from: AudioChannels,
+    to: AudioChannels,
+    // One row for each output channel, of one gain for each input channel.
+    gains: Vec<f64>,
+}
+
+impl MixMatrix {
+    /// Create the standard up/down-mix matrix between two layouts.
+    /// Speakers missing from the output are folded into their neighbours
+    /// (ITU-R BS.775 downmix), LFE is dropped, and mono is copied to both
+    /// front speakers.  Downmixes are scaled so that full-scale input can't
+    /// clip.
+    pub fn new(from: AudioChannels, to: AudioChannels) -> MixMatrix {
+        let (n_in, n_out) = (from.n_channels(), to.n_channels());
+        let mut gains = vec![0.0; n_in * n_out];
+        for (input, speaker) in from.speakers().into_iter().enumerate() {
+            let mut add = |output: usize, gain: f64| gains[output * n_in + input] += gain;
+            if from == AudioChannels::Mono && !to.has(Speaker::FrontCenter) {
+                for speaker in &[Speaker::FrontLeft, Speaker::FrontRight] {
+                    if let Some(output) = to.channel(*speaker) {
+                        add(output, 1.0);
+                    }
+                }
+            } else {
+                fold(speaker, 1.0, 2, to, &mut add);
+            }
+        }
+        MixMatrix::custom(from, to, gains).normalized()
+    }
+
+    /// Create a mix matrix from a list of gains: one row for each output
+    /// channel, of one gain for each input channel.  Panics if the number of
+    /// gains doesn't match the layouts.
+    pub fn custom(from: AudioChannels, to: AudioChannels, gains: Vec<f64>) -> MixMatrix {
+        assert_eq!(
+            gains.len(),
+            from.n_channels() * to.n_channels(),
+            "Mix matrix must have a gain for each input of each output"
+        );
+        MixMatrix { from, to, gains }
+    }
+
+    /// Get the input channel layout.
+    pub fn from(&self) -> AudioChannels {
+        self.from
+    }
+
+    /// Get the output channel layout.
+    pub fn to(&self) -> AudioChannels {
+        self.to
+    }
+
+    /// Get the gain of an input channel in an output channel.
+    pub fn gain(&self, output: usize, input: usize) -> f64 {
+        self.gains[output * self.from.n_channels() + input]
+    }
+
+    /// Scale down rows that could clip, so each output's gains sum to at
+    /// most 1.0.
+    pub fn normalized(mut self) -> MixMatrix {
+        let n = self.from.n_channels();
+        for row in self.gains.chunks_mut(n) {
+            let sum: f64 = row.iter().map(|g| g.abs()).sum();
+            if sum > 1.0 {
+                for gain in row {
+                    *gain /= sum;
+                }
+            }
+        }
+        self
+    }
+
+    /// Mix a frame, keeping its sample format and order.
+    pub fn mix(&self, frame: &AFrame) -> AFrame {
+        assert_eq!(frame.channels(), self.from, "Frame doesn't match mix input");
+        let (n_in, n_out, len) = (self.from.n_channels(), self.to.n_channels(), frame.len());
+        let samples = Samples::silence(frame.format(), len * n_out);
+        let mut out = AFrame::new(self.to, frame.layout(), samples);
+        for index in 0..len {
+            for o in 0..n_out {
+                let sum = (0..n_in)
+                    .map(|i| self.gain(o, i) * frame.get(i, index))
+                    .sum();
+                out.set(o, index, sum);
+            }
+        }
+        out
+    }
+
+    /// Mix the next frame on the `Audio` queue.  Returns `None` if the queue
+    /// is empty.
+    pub fn run(&self, audio: &mut Audio) -> Option<AFrame> {
+        let frame = audio.pop()?;
+        Some(self.mix(&frame))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use AudioChannels::*;
+
+    #[test]
+    fn surround_to_stereo() {
+        let matrix = MixMatrix::new(Surround, Stereo);
+        let sum = 1.0 + 2.0 * FRAC_1_SQRT_2;
+        assert!((matrix.gain(0, 0) - 1.0 / sum).abs() < 1e-12);
+        assert!((matrix.gain(1, 2) - FRAC_1_SQRT_2 / sum).abs() < 1e-12);
+        assert_eq!(matrix.gain(0, 4), 0.0);
+
+        // Full scale on every channel doesn't clip.
+        let frame = AFrame::interleaved(Surround, vec![1.0f32; 5]);
+        let out = matrix.mix(&frame);
+        assert!((out.get(0, 0) - 1.0).abs() < 1e-6);
+
+        // A custom layout with the same speakers is the same layout.
+        let frame = AFrame::interleaved(Custom(Surround.mask()), vec![1.0f32; 5]);
+        assert_eq!(matrix.mix(&frame).len(), 1);
+    }
+
+    #[test]
+    fn surround_51_to_mono() {
+        let matrix = MixMatrix::new(Surround51, Mono);
+        let h = FRAC_1_SQRT_2;
+        let sum = 1.0 + 2.0 * h + 2.0 * 0.5;
+        assert!((matrix.gain(0, 0) - h / sum).abs() < 1e-12);
+        assert!((matrix.gain(0, 2) - 1.0 / sum).abs() < 1e-12);
+        assert_eq!(matrix.gain(0, 3), 0.0);
+        assert!((matrix.gain(0, 5) - 0.5 / sum).abs() < 1e-12);
+    }
+
+    #[test]
+    fn round_trip_mono() {
+        let frame = AFrame::interleaved(Mono, vec![0.25f64, -0.5]);
+        let stereo = MixMatrix::new(Mono, Stereo).mix(&frame);
+        assert_eq!(stereo.get(1, 1), -0.5);
+        let mono = MixMatrix::new(Stereo, Mono).mix(&stereo);
+        assert_eq!(mono.samples(), frame.samples());
+    }
+}
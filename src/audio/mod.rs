@@ -0,0 +1,361 @@
+// Copyright Jeron Lau 2017 - 2018.
+// Dual-licensed under either the MIT License or the Boost Software License, Version 1.0.
+// (See accompanying file LICENSE_1_0.txt or copy at https://www.boost.org/LICENSE_1_0.txt)
+
+mod channels;
+mod mix;
+mod resample;
+mod sample;
+
+pub use self::channels::AudioChannels::*;
+pub use self::channels::{AudioChannels, ChannelOrder, Speaker};
+pub use self::mix::MixMatrix;
+pub use self::resample::{ResampleQuality, Resampler};
+pub use self::sample::{Dither, SampleFormat, Samples};
+
+use std::collections::vec_deque;
+use std::collections::VecDeque;
+use std::ops;
+use Metadata;
+
+/// How the samples in an `AFrame` are ordered.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum SampleLayout {
+    /// One sample from each channel, then the next (LRLRLR).
+    Interleaved,
+    /// All samples from the first channel, then the next channel (LLLRRR).
+    Planar,
+}
+
+pub use SampleLayout::*;
+
+/// An audio frame: any number of samples for each channel.
+#[derive(Clone)]
+pub struct AFrame {
+    samples: Samples,
+    channels: AudioChannels,
+    layout: SampleLayout,
+}
+
+impl AFrame {
+    /// Create a new audio frame.  Panics if the layout has no channels, or
+    /// if the number of samples isn't a multiple of the number of channels.
+    pub fn new<S: Into<Samples>>(
+        channels: AudioChannels,
+        layout: SampleLayout,
+        samples: S,
+    ) -> AFrame {
+        let samples = samples.into();
+        assert!(channels.n_channels() != 0, "Channel layout has no speakers");
+        assert_eq!(
+            samples.len() % channels.n_channels(),
+            0,
+            "Sample count must be a multiple of the channel count"
+        );
+        AFrame {
+            samples,
+            channels,
+            layout,
+        }
+    }
+
+    /// Create a new audio frame from interleaved samples (LRLRLR).
+    pub fn interleaved<S: Into<Samples>>(channels: AudioChannels, samples: S) -> AFrame {
+        AFrame::new(channels, Interleaved, samples)
+    }
+
+    /// Create a new audio frame from planar samples (LLLRRR).
+    pub fn planar<S: Into<Samples>>(channels: AudioChannels, samples: S) -> AFrame {
+        AFrame::new(channels, Planar, samples)
+    }
+
+    /// Get the channel layout.
+    pub fn channels(&self) -> AudioChannels {
+        self.channels
+    }
+
+    /// Get the number of channels.
+    pub fn n_channels(&self) -> usize {
+        self.channels.n_channels()
+    }
+
+    /// Get how the samples are ordered.
+    pub fn layout(&self) -> SampleLayout {
+        self.layout
+    }
+
+    /// Get the format of the samples.
+    pub fn format(&self) -> SampleFormat {
+        self.samples.format()
+    }
+
+    /// Get the number of samples for each channel.
+    pub fn len(&self) -> usize {
+        self.samples.len() / self.n_channels()
+    }
+
+    /// Return true if there are no samples.
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Get all of the samples, in the order of `layout()`.
+    pub fn samples(&self) -> &Samples {
+        &self.samples
+    }
+
+    /// Take all of the samples, in the order of `layout()`.
+    pub fn into_samples(self) -> Samples {
+        self.samples
+    }
+
+    /// Get the offset into `samples()` for a sample in a channel.
+    #[inline(always)]
+    fn offset(&self, channel: usize, index: usize) -> usize {
+        match self.layout {
+            Interleaved => index * self.n_channels() + channel,
+            Planar => channel * self.len() + index,
+        }
+    }
+
+    /// Get a sample from a channel (-1.0 to 1.0).
+    pub fn get(&self, channel: usize, index: usize) -> f64 {
+        self.samples.get(self.offset(channel, index))
+    }
+
+    /// Set a sample in a channel (-1.0 to 1.0).
+    pub fn set(&mut self, channel: usize, index: usize, sample: f64) {
+        let offset = self.offset(channel, index);
+        self.samples.set(offset, sample);
+    }
+
+    /// Iterate over the samples of a channel (-1.0 to 1.0).
+    pub fn channel<'a>(&'a self, channel: usize) -> impl Iterator<Item = f64> + 'a {
+        assert!(channel < self.n_channels(), "No such channel");
+        (0..self.len()).map(move |i| self.get(channel, i))
+    }
+
+    /// Convert the frame to another sample order.
+    pub fn to_layout(&self, layout: SampleLayout) -> AFrame {
+        if layout == self.layout {
+            return self.clone();
+        }
+
+        let (nc, len) = (self.n_channels(), self.len());
+        let samples = match layout {
+            Interleaved => self.samples.pick(nc * len, |i| (i % nc) * len + i / nc),
+            Planar => self.samples.pick(nc * len, |i| (i % len) * nc + i / len),
+        };
+        AFrame::new(self.channels, layout, samples)
+    }
+
+    /// Convert the frame from one codec's channel order to another's.
+    pub fn reorder(&self, from: ChannelOrder, to: ChannelOrder) -> AFrame {
+        let from = from.speakers(self.channels);
+        let map: Vec<usize> = to
+            .speakers(self.channels)
+            .iter()
+            .map(|s| from.iter().position(|f| f == s).unwrap())
+            .collect();
+        let (nc, len) = (self.n_channels(), self.len());
+        let samples = match self.layout {
+            Interleaved => self.samples.pick(nc * len, |i| (i / nc) * nc + map[i % nc]),
+            Planar => self
+                .samples
+                .pick(nc * len, |i| map[i / len] * len + i % len),
+        };
+        AFrame::new(self.channels, self.layout, samples)
+    }
+
+    /// Convert the frame to another sample format, advancing `dither`.
+    pub fn to_format(&self, format: SampleFormat, dither: &mut Dither) -> AFrame {
+        AFrame::new(
+            self.channels,
+            self.layout,
+            format.from(&self.samples, dither),
+        )
+    }
+}
+
+/// An Audio Buffer.
+pub struct Audio {
+    /// Title, artist, album, cover art, etc.
+    pub metadata: Metadata,
+    /// The actual audio.
+    frames: VecDeque<AFrame>,
+    /// The total number of frames in the audio.
+    n_frames: u32,
+    /// Samples per second.
+    sample_rate: u32,
+    /// The channel layout.
+    channels: AudioChannels,
+}
+
+impl Audio {
+    /// Create a new `Audio`, with `sample_rate` samples per second for each
+    /// channel (44_100, 48_000, etc.)
+    pub fn new(channels: AudioChannels, sample_rate: u32, n_frames: u32) -> Audio {
+        Audio {
+            n_frames,
+            sample_rate,
+            channels,
+            frames: VecDeque::new(),
+            metadata: Metadata::new(),
+        }
+    }
+
+    /// Add frame to the buffer.  Panics if the frame's channels don't match.
+    pub fn add(&mut self, frame: AFrame) {
+        assert_eq!(
+            frame.channels(),
+            self.channels,
+            "AFrame channels don't match Audio"
+        );
+        self.frames.push_back(frame);
+    }
+
+    /// Add a frame of interleaved samples (LRLRLR) to the buffer.
+    pub fn add_samples<T: Clone>(&mut self, samples: &[T])
+    where
+        Vec<T>: Into<Samples>,
+    {
+        self.add(AFrame::interleaved(self.channels, samples.to_vec()));
+    }
+
+    /// Returns audio for the next frame on the Queue.
+    pub fn pop(&mut self) -> Option<AFrame> {
+        self.frames.pop_front()
+    }
+
+    /// Return the number frames in the buffer.
+    pub fn len(&self) -> u32 {
+        self.frames.len() as u32
+    }
+
+    /// Return true if there are no frames in the buffer.
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Get a frame in the buffer.
+    pub fn get(&self, index: usize) -> Option<&AFrame> {
+        self.frames.get(index)
+    }
+
+    /// Get a frame in the buffer, mutably.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut AFrame> {
+        self.frames.get_mut(index)
+    }
+
+    /// Iterate over the frames in the buffer.
+    pub fn iter(&self) -> vec_deque::Iter<'_, AFrame> {
+        self.frames.iter()
+    }
+
+    /// Iterate over the frames in the buffer, mutably.
+    pub fn iter_mut(&mut self) -> vec_deque::IterMut<'_, AFrame> {
+        self.frames.iter_mut()
+    }
+
+    /// Iterate over the samples of a channel (-1.0 to 1.0), across all of the
+    /// frames in the buffer.
+    pub fn samples<'a>(&'a self, channel: usize) -> impl Iterator<Item = f64> + 'a {
+        self.frames.iter().flat_map(move |f| f.channel(channel))
+    }
+
+    /// Return the number of samples for each channel in the buffer.
+    pub fn n_samples(&self) -> usize {
+        self.frames.iter().map(AFrame::len).sum()
+    }
+
+    /// Return the number of frames in the audio.
+    pub fn frames(&self) -> u32 {
+        self.n_frames
+    }
+
+    /// Get the number of samples per second, for each channel.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Get the channel layout.
+    pub fn channels(&self) -> AudioChannels {
+        self.channels
+    }
+
+    /// Return the number of channels.
+    pub fn n_channels(&self) -> usize {
+        self.channels.n_channels()
+    }
+}
+
+impl ops::Index<usize> for Audio {
+    type Output = AFrame;
+
+    fn index(&self, index: usize) -> &AFrame {
+        &self.frames[index]
+    }
+}
+
+impl ops::IndexMut<usize> for Audio {
+    fn index_mut(&mut self, index: usize) -> &mut AFrame {
+        &mut self.frames[index]
+    }
+}
+
+impl<'a> IntoIterator for &'a Audio {
+    type Item = &'a AFrame;
+    type IntoIter = vec_deque::Iter<'a, AFrame>;
+
+    fn into_iter(self) -> vec_deque::Iter<'a, AFrame> {
+        self.frames.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_round_trip() {
+        let frame = AFrame::interleaved(Stereo, vec![0i16, 10, 1, 11, 2, 12]);
+        assert_eq!(frame.len(), 3);
+        let planar = frame.to_layout(Planar);
+        assert_eq!(planar.samples(), &Samples::I16(vec![0, 1, 2, 10, 11, 12]));
+        assert_eq!(planar.get(1, 2), 12.0 / 32768.0);
+        assert_eq!(planar.to_layout(Interleaved).samples(), frame.samples());
+    }
+
+    #[test]
+    #[should_panic(expected = "Channel layout has no speakers")]
+    fn no_channels() {
+        AFrame::interleaved(AudioChannels::Custom(0), vec![0i16; 2]);
+    }
+
+    #[test]
+    fn reorder_ac3() {
+        // WAV order: FL FR FC LFE BL BR
+        let frame = AFrame::interleaved(Surround51, vec![0i16, 1, 2, 3, 4, 5]);
+        let ac3 = frame.reorder(ChannelOrder::Wav, ChannelOrder::Ac3);
+        assert_eq!(ac3.samples(), &Samples::I16(vec![0, 2, 1, 4, 5, 3]));
+        let wav = ac3
+            .to_layout(Planar)
+            .reorder(ChannelOrder::Ac3, ChannelOrder::Wav);
+        assert_eq!(wav.to_layout(Interleaved).samples(), frame.samples());
+    }
+
+    #[test]
+    fn fill_and_iterate() {
+        let mut audio = Audio::new(Stereo, 44_100, 2);
+        audio.add_samples(&[0i16, 16384, 8192, -16384]);
+        audio.add(AFrame::planar(Stereo, vec![0.75f32, -0.25]));
+        assert_eq!(audio.len(), 2);
+        assert_eq!(audio.n_samples(), 3);
+        assert_eq!(audio[1].format(), SampleFormat::F32);
+        let left: Vec<f64> = audio.samples(0).collect();
+        assert_eq!(left, vec![0.0, 0.25, 0.75]);
+        let right: Vec<f64> = audio.samples(1).collect();
+        assert_eq!(right, vec![0.5, -0.5, -0.25]);
+        assert_eq!(audio.iter().count(), 2);
+    }
+}
@@ -0,0 +1,278 @@
+// Copyright Jeron Lau 2017 - 2018.
+// Dual-licensed under either the MIT License or the Boost Software License, Version 1.0.
+// (See accompanying file LICENSE_1_0.txt or copy at https://www.boost.org/LICENSE_1_0.txt)
+
+use std::f64::consts::PI;
+
+use AFrame;
+use Audio;
+use AudioChannels;
+use SampleFormat;
+use SampleLayout;
+use Samples;
+
+/// The largest number of filter phases to store before interpolating.
+const MAX_PHASES: u64 = 1024;
+
+/// Quality presets for the `Resampler`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum ResampleQuality {
+    /// Short filter, for realtime previews.
+    Fast,
+    /// Good enough for most music and video.
+    #[default]
+    Medium,
+    /// Long filter with a steep cutoff, for mastering.
+    Best,
+}
+
+impl ResampleQuality {
+    /// (zero crossings, Kaiser window beta, passband fraction of Nyquist)
+    fn params(self) -> (f64, f64, f64) {
+        match self {
+            ResampleQuality::Fast => (8.0, 6.0, 0.90),
+            ResampleQuality::Medium => (16.0, 8.6, 0.95),
+            ResampleQuality::Best => (32.0, 10.0, 0.97),
+        }
+    }
+}
+
+/// Greatest common divisor.
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        let t = a % b;
+        a = b;
+        b = t;
+    }
+    a
+}
+
+/// Zeroth order modified Bessel function of the first kind.
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let mut k = 1.0;
+    while term > sum * 1e-12 {
+        term *= (x / (2.0 * k)) * (x / (2.0 * k));
+        sum += term;
+        k += 1.0;
+    }
+    sum
+}
+
+/// Converts audio between sample rates with a Kaiser-windowed sinc filter,
+/// one frame at a time.
+pub struct Resampler {
+    // Ratio in lowest terms: `up` output samples for every `down` input.
+    up: u64,
+    down: u64,
+    to: u32,
+    channels: AudioChannels,
+    // Filter reaches `half` input samples on each side.
+    half: i64,
+    // Number of phases stored in `table`.
+    phases: u64,
+    // `phases + 1` rows of `2 * half` coefficients.
+    table: Vec<f64>,
+    // Input samples for each channel, starting at absolute index `base`.
+    history: Vec<Vec<f64>>,
+    base: u64,
+    // Total input samples received, and output samples produced.
+    consumed: u64,
+    produced: u64,
+    // Format of the last frame, used for the output of `end()`.
+    format: SampleFormat,
+    layout: SampleLayout,
+}
+
+impl Resampler {
+    /// Create a new resampler from the sample rate of `audio` to
+    /// `sample_rate`.
+    pub fn new(audio: &Audio, sample_rate: u32, quality: ResampleQuality) -> Resampler {
+        let from = audio.sample_rate() as u64;
+        let to = sample_rate as u64;
+        assert!(from != 0 && to != 0, "Sample rate can't be zero");
+        let g = gcd(from, to);
+        let (up, down) = (to / g, from / g);
+        let (zeros, beta, pass) = quality.params();
+
+        // Cutoff relative to the input Nyquist, lowered when downsampling.
+        let cutoff = pass * (up as f64 / down as f64).min(1.0);
+        let half = (zeros / cutoff).ceil() as i64;
+        let phases = up.min(MAX_PHASES);
+        let width = 2 * half as usize;
+        let i0_beta = bessel_i0(beta);
+
+        let mut table = Vec::with_capacity((phases as usize + 1) * width);
+        for phase in 0..=phases {
+            let frac = phase as f64 / phases as f64;
+            for k in (1 - half)..=half {
+                let x = k as f64 - frac;
+                let sinc = if x == 0.0 {
+                    1.0
+                } else {
+                    (PI * cutoff * x).sin() / (PI * cutoff * x)
+                };
+                let r = x / half as f64;
+                let window = if r.abs() >= 1.0 {
+                    0.0
+                } else {
+                    bessel_i0(beta * (1.0 - r * r).sqrt()) / i0_beta
+                };
+                table.push(cutoff * sinc * window);
+            }
+        }
+
+        Resampler {
+            up,
+            down,
+            to: sample_rate,
+            channels: audio.channels(),
+            half,
+            phases,
+            table,
+            history: vec![Vec::new(); audio.n_channels()],
+            base: 0,
+            consumed: 0,
+            produced: 0,
+            format: SampleFormat::F32,
+            layout: SampleLayout::Interleaved,
+        }
+    }
+
+    /// Get the output sample rate.
+    pub fn sample_rate(&self) -> u32 {
+        self.to
+    }
+
+    /// Resample the next frame on the `Audio` queue.  Returns `None` if the
+    /// queue is empty.
+    pub fn run(&mut self, audio: &mut Audio) -> Option<AFrame> {
+        let frame = audio.pop()?;
+        Some(self.process(&frame))
+    }
+
+    /// Resample a frame.  The output is delayed by the filter length, so it
+    /// may have fewer samples than expected until `end()` is called.
+    pub fn process(&mut self, frame: &AFrame) -> AFrame {
+        assert_eq!(
+            frame.n_channels(),
+            self.history.len(),
+            "Channel count changed"
+        );
+        self.format = frame.format();
+        self.layout = frame.layout();
+        for (channel, history) in self.history.iter_mut().enumerate() {
+            history.extend((0..frame.len()).map(|i| frame.get(channel, i)));
+        }
+        self.consumed += frame.len() as u64;
+
+        // Output `n` needs input up to `n * down / up + half`.
+        let avail = self.consumed as i64 - self.half;
+        let ready = if avail <= 0 {
+            0
+        } else {
+            (avail as u64 * self.up).div_ceil(self.down)
+        };
+        self.output(ready)
+    }
+
+    /// Finish resampling, returning the remaining samples.
+    pub fn end(mut self) -> AFrame {
+        let pad = self.half as usize;
+        for history in &mut self.history {
+            history.extend((0..pad).map(|_| 0.0));
+        }
+        let total = (self.consumed * self.up).div_ceil(self.down);
+        self.output(total)
+    }
+
+    /// Produce output samples up to (not including) sample `until`.
+    fn output(&mut self, until: u64) -> AFrame {
+        let len = until.saturating_sub(self.produced) as usize;
+        let samples = Samples::silence(self.format, len * self.history.len());
+        let mut out = AFrame::new(self.channels, self.layout, samples);
+        let width = 2 * self.half as usize;
+
+        for i in 0..len {
+            let n = self.produced + i as u64;
+            let center = (n * self.down / self.up) as i64;
+            let pos = (n * self.down % self.up) * self.phases;
+            let row = (pos / self.up) as usize;
+            let blend = (pos % self.up) as f64 / self.up as f64;
+            let (a, b) = (row * width, (row + 1).min(self.phases as usize) * width);
+
+            for (channel, history) in self.history.iter().enumerate() {
+                let mut sum = 0.0;
+                for k in 0..width {
+                    let index = center + 1 - self.half + k as i64 - self.base as i64;
+                    if index < 0 || index as usize >= history.len() {
+                        continue;
+                    }
+                    let coef = self.table[a + k] + (self.table[b + k] - self.table[a + k]) * blend;
+                    sum += history[index as usize] * coef;
+                }
+                out.set(channel, i, sum);
+            }
+        }
+        self.produced += len as u64;
+
+        // Drop history that no future output sample can reach.
+        let next = (self.produced * self.down / self.up) as i64 + 1 - self.half;
+        if next > self.base as i64 {
+            let drop = (next as u64 - self.base) as usize;
+            for history in &mut self.history {
+                let drop = drop.min(history.len());
+                history.drain(..drop);
+            }
+            self.base = next as u64;
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(rate: f64, hz: f64, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| (0.5 * (2.0 * PI * hz * i as f64 / rate).sin()) as f32)
+            .collect()
+    }
+
+    #[test]
+    fn length_and_amplitude() {
+        let audio = Audio::new(AudioChannels::Mono, 44_100, 0);
+        let mut resampler = Resampler::new(&audio, 48_000, ResampleQuality::Medium);
+        let input = sine(44_100.0, 1000.0, 44_100);
+        let mut output = Vec::new();
+        for chunk in input.chunks(1000) {
+            let frame = AFrame::interleaved(AudioChannels::Mono, chunk.to_vec());
+            let out = resampler.process(&frame);
+            output.extend((0..out.len()).map(|i| out.get(0, i)));
+        }
+        let out = resampler.end();
+        output.extend((0..out.len()).map(|i| out.get(0, i)));
+        assert_eq!(output.len(), 48_000);
+
+        // Compare against an ideal sine at the new rate, away from the edges.
+        let ideal = sine(48_000.0, 1000.0, 48_000);
+        for i in 1000..47_000 {
+            assert!((output[i] - ideal[i] as f64).abs() < 0.002);
+        }
+    }
+
+    #[test]
+    fn downsample_removes_aliases() {
+        let audio = Audio::new(AudioChannels::Mono, 48_000, 0);
+        let mut resampler = Resampler::new(&audio, 8_000, ResampleQuality::Best);
+        // 6kHz is above the 4kHz Nyquist of the output.
+        let frame = AFrame::interleaved(AudioChannels::Mono, sine(48_000.0, 6000.0, 48_000));
+        let out = resampler.process(&frame);
+        for i in 500..out.len() {
+            assert!(out.get(0, i).abs() < 0.01);
+        }
+    }
+}
@@ -0,0 +1,326 @@
+// Copyright Jeron Lau 2017 - 2018.
+// Dual-licensed under either the MIT License or the Boost Software License, Version 1.0.
+// (See accompanying file LICENSE_1_0.txt or copy at https://www.boost.org/LICENSE_1_0.txt)
+
+/// The format of each sample in an `AFrame`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum SampleFormat {
+    /// Signed 8-bit integer.
+    I8,
+    /// Signed 16-bit integer.
+    I16,
+    /// Signed 24-bit integer (stored sign-extended in an `i32`).
+    I24,
+    /// Signed 32-bit integer.
+    I32,
+    /// 32-bit float (-1.0 to 1.0).
+    F32,
+    /// 64-bit float (-1.0 to 1.0).
+    F64,
+}
+
+impl SampleFormat {
+    /// Return the number of significant bits in a sample.
+    pub fn bits(self) -> u32 {
+        match self {
+            SampleFormat::I8 => 8,
+            SampleFormat::I16 => 16,
+            SampleFormat::I24 => 24,
+            SampleFormat::I32 | SampleFormat::F32 => 32,
+            SampleFormat::F64 => 64,
+        }
+    }
+
+    /// Return true if the samples are floating point.
+    pub fn is_float(self) -> bool {
+        matches!(self, SampleFormat::F32 | SampleFormat::F64)
+    }
+
+    /// Convert samples to this format from another.  Widening conversions
+    /// are lossless, narrowing conversions round (to the nearest value, or
+    /// with `dither` noise) and clip.  The `dither` state is advanced, so
+    /// reuse it for each frame of a stream.
+    pub fn from(self, samples: &Samples, dither: &mut Dither) -> Samples {
+        if samples.format() == self {
+            return samples.clone();
+        }
+
+        let len = samples.len();
+        match self {
+            SampleFormat::I8 => Samples::I8(
+                (0..len)
+                    .map(|i| samples.to_int(i, 8, dither) as i8)
+                    .collect(),
+            ),
+            SampleFormat::I16 => Samples::I16(
+                (0..len)
+                    .map(|i| samples.to_int(i, 16, dither) as i16)
+                    .collect(),
+            ),
+            SampleFormat::I24 => {
+                Samples::I24((0..len).map(|i| samples.to_int(i, 24, dither)).collect())
+            }
+            SampleFormat::I32 => {
+                Samples::I32((0..len).map(|i| samples.to_int(i, 32, dither)).collect())
+            }
+            SampleFormat::F32 => Samples::F32((0..len).map(|i| samples.get(i) as f32).collect()),
+            SampleFormat::F64 => Samples::F64((0..len).map(|i| samples.get(i)).collect()),
+        }
+    }
+}
+
+/// Noise added when reducing the bit depth of samples.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Dither {
+    /// No dither, round to the nearest value.
+    None,
+    /// Triangular (TPDF) dither of ±1 LSB, with the state of its
+    /// pseudo-random number generator (any seed, see `Dither::triangular`).
+    Triangular(u32),
+}
+
+impl Dither {
+    /// Triangular dither with the default seed.
+    pub fn triangular() -> Dither {
+        Dither::Triangular(0x2545_F491)
+    }
+
+    /// Pseudo-random number generator for dither (xorshift32).
+    fn next(state: &mut u32) -> u32 {
+        // Xorshift gets stuck on zero.
+        if *state == 0 {
+            *state = 0x2545_F491;
+        }
+        *state ^= *state << 13;
+        *state ^= *state >> 17;
+        *state ^= *state << 5;
+        *state
+    }
+
+    /// Triangular noise of ±1 LSB, where 1 LSB is `1 << shift`.
+    fn noise(&mut self, shift: u32) -> i64 {
+        match *self {
+            Dither::None => 0,
+            Dither::Triangular(ref mut state) => {
+                let mask = (1u64 << shift) - 1;
+                let a = Dither::next(state) as u64 & mask;
+                a as i64 - (Dither::next(state) as u64 & mask) as i64
+            }
+        }
+    }
+
+    /// Triangular noise of ±1.0.
+    fn noise_f64(&mut self) -> f64 {
+        match *self {
+            Dither::None => 0.0,
+            Dither::Triangular(ref mut state) => {
+                let scale = 1.0 / (u32::MAX as f64 + 1.0);
+                let a = Dither::next(state) as f64;
+                (a - Dither::next(state) as f64) * scale
+            }
+        }
+    }
+}
+
+/// The samples of an `AFrame`, in one of the `SampleFormat`s.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Samples {
+    /// Signed 8-bit integer samples.
+    I8(Vec<i8>),
+    /// Signed 16-bit integer samples.
+    I16(Vec<i16>),
+    /// Signed 24-bit integer samples (sign-extended).
+    I24(Vec<i32>),
+    /// Signed 32-bit integer samples.
+    I32(Vec<i32>),
+    /// 32-bit float samples.
+    F32(Vec<f32>),
+    /// 64-bit float samples.
+    F64(Vec<f64>),
+}
+
+impl From<Vec<i8>> for Samples {
+    fn from(v: Vec<i8>) -> Samples {
+        Samples::I8(v)
+    }
+}
+
+impl From<Vec<i16>> for Samples {
+    fn from(v: Vec<i16>) -> Samples {
+        Samples::I16(v)
+    }
+}
+
+impl From<Vec<i32>> for Samples {
+    fn from(v: Vec<i32>) -> Samples {
+        Samples::I32(v)
+    }
+}
+
+impl From<Vec<f32>> for Samples {
+    fn from(v: Vec<f32>) -> Samples {
+        Samples::F32(v)
+    }
+}
+
+impl From<Vec<f64>> for Samples {
+    fn from(v: Vec<f64>) -> Samples {
+        Samples::F64(v)
+    }
+}
+
+impl Samples {
+    /// Create `len` samples of silence.
+    pub fn silence(format: SampleFormat, len: usize) -> Samples {
+        match format {
+            SampleFormat::I8 => Samples::I8(vec![0; len]),
+            SampleFormat::I16 => Samples::I16(vec![0; len]),
+            SampleFormat::I24 => Samples::I24(vec![0; len]),
+            SampleFormat::I32 => Samples::I32(vec![0; len]),
+            SampleFormat::F32 => Samples::F32(vec![0.0; len]),
+            SampleFormat::F64 => Samples::F64(vec![0.0; len]),
+        }
+    }
+
+    /// Get the format of the samples.
+    pub fn format(&self) -> SampleFormat {
+        match *self {
+            Samples::I8(_) => SampleFormat::I8,
+            Samples::I16(_) => SampleFormat::I16,
+            Samples::I24(_) => SampleFormat::I24,
+            Samples::I32(_) => SampleFormat::I32,
+            Samples::F32(_) => SampleFormat::F32,
+            Samples::F64(_) => SampleFormat::F64,
+        }
+    }
+
+    /// Get the number of samples.
+    pub fn len(&self) -> usize {
+        match *self {
+            Samples::I8(ref v) => v.len(),
+            Samples::I16(ref v) => v.len(),
+            Samples::I24(ref v) | Samples::I32(ref v) => v.len(),
+            Samples::F32(ref v) => v.len(),
+            Samples::F64(ref v) => v.len(),
+        }
+    }
+
+    /// Return true if there are no samples.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get a sample as a float from -1.0 to 1.0.  This is lossless for
+    /// every format.
+    pub fn get(&self, index: usize) -> f64 {
+        match *self {
+            Samples::F32(ref v) => v[index] as f64,
+            Samples::F64(ref v) => v[index],
+            _ => self.justified(index) as f64 / 2_147_483_648.0,
+        }
+    }
+
+    /// Set a sample from a float from -1.0 to 1.0, rounding and clipping for
+    /// integer formats.
+    pub fn set(&mut self, index: usize, sample: f64) {
+        let int = |bits: u32| {
+            let max = (1i64 << (bits - 1)) as f64;
+            (sample * max).round().max(-max).min(max - 1.0) as i32
+        };
+        match *self {
+            Samples::I8(ref mut v) => v[index] = int(8) as i8,
+            Samples::I16(ref mut v) => v[index] = int(16) as i16,
+            Samples::I24(ref mut v) => v[index] = int(24),
+            Samples::I32(ref mut v) => v[index] = int(32),
+            Samples::F32(ref mut v) => v[index] = sample as f32,
+            Samples::F64(ref mut v) => v[index] = sample,
+        }
+    }
+
+    /// Create new samples by picking samples from these ones by index.
+    pub(crate) fn pick<F: Fn(usize) -> usize>(&self, len: usize, f: F) -> Samples {
+        match *self {
+            Samples::I8(ref v) => Samples::I8((0..len).map(|i| v[f(i)]).collect()),
+            Samples::I16(ref v) => Samples::I16((0..len).map(|i| v[f(i)]).collect()),
+            Samples::I24(ref v) => Samples::I24((0..len).map(|i| v[f(i)]).collect()),
+            Samples::I32(ref v) => Samples::I32((0..len).map(|i| v[f(i)]).collect()),
+            Samples::F32(ref v) => Samples::F32((0..len).map(|i| v[f(i)]).collect()),
+            Samples::F64(ref v) => Samples::F64((0..len).map(|i| v[f(i)]).collect()),
+        }
+    }
+
+    /// Get an integer sample, shifted to fill 32 bits.
+    fn justified(&self, index: usize) -> i64 {
+        match *self {
+            Samples::I8(ref v) => (v[index] as i64) << 24,
+            Samples::I16(ref v) => (v[index] as i64) << 16,
+            Samples::I24(ref v) => (v[index] as i64) << 8,
+            Samples::I32(ref v) => v[index] as i64,
+            Samples::F32(_) | Samples::F64(_) => unreachable!(),
+        }
+    }
+
+    /// Get a sample as an integer with `bits` significant bits.
+    fn to_int(&self, index: usize, bits: u32, dither: &mut Dither) -> i32 {
+        let max = (1i64 << (bits - 1)) - 1;
+        let min = -(1i64 << (bits - 1));
+        let format = self.format();
+        let v = if format.is_float() {
+            let v = self.get(index) * (1i64 << (bits - 1)) as f64;
+            (v + dither.noise_f64()).round() as i64
+        } else if format.bits() <= bits {
+            // Lossless: the low bits are all zero.
+            self.justified(index) >> (32 - bits)
+        } else {
+            let shift = 32 - bits;
+            let v = self.justified(index) + dither.noise(shift);
+            (v + (1 << (shift - 1))) >> shift
+        };
+        v.clamp(min, max) as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lossless_widening() {
+        let src = Samples::I16(vec![i16::MIN, -1, 0, 1, i16::MAX]);
+        for format in [
+            SampleFormat::I24,
+            SampleFormat::I32,
+            SampleFormat::F32,
+            SampleFormat::F64,
+        ] {
+            let wide = format.from(&src, &mut Dither::triangular());
+            assert_eq!(SampleFormat::I16.from(&wide, &mut Dither::None), src);
+        }
+    }
+
+    #[test]
+    fn narrowing_clips_and_rounds() {
+        let src = Samples::F32(vec![2.0, -2.0, 0.5, 0.0]);
+        let dst = SampleFormat::I16.from(&src, &mut Dither::None);
+        assert_eq!(dst, Samples::I16(vec![i16::MAX, i16::MIN, 16384, 0]));
+
+        let src = Samples::I24(vec![0x7F, 0x80, -0x80]);
+        let dst = SampleFormat::I16.from(&src, &mut Dither::None);
+        assert_eq!(dst, Samples::I16(vec![0, 1, 0]));
+    }
+
+    #[test]
+    fn dither_within_one_lsb() {
+        let src = Samples::I24((0..1000).map(|i| i * 97 - 48_000).collect());
+        let mut dither = Dither::triangular();
+        let dst = SampleFormat::I16.from(&src, &mut dither);
+        for i in 0..src.len() {
+            let exact = src.get(i) * 32768.0;
+            let got = dst.get(i) * 32768.0;
+            assert!((exact - got).abs() <= 1.5);
+        }
+        // The next frame gets different noise.
+        assert_ne!(dither, Dither::triangular());
+        assert_ne!(SampleFormat::I16.from(&src, &mut dither), dst);
+    }
+}
@@ -0,0 +1,194 @@
+// Copyright Jeron Lau 2017 - 2018.
+// Dual-licensed under either the MIT License or the Boost Software License, Version 1.0.
+// (See accompanying file LICENSE_1_0.txt or copy at https://www.boost.org/LICENSE_1_0.txt)
+
+use Audio;
+use ColorChannels;
+use Error;
+use Result;
+use SampleFormat;
+use Video;
+
+/// What a codec can do, so a pipeline can pick one and convert `Video` and
+/// `Audio` into a format it supports first.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Capabilities {
+    /// Supported `ColorChannels`, best first (empty if video isn't
+    /// supported).
+    pub colors: &'static [ColorChannels],
+    /// The largest supported width and height.
+    pub max_wh: (u16, u16),
+    /// Whether more than one video frame (animation) is supported.
+    pub animation: bool,
+    /// Supported sample formats, best first (empty if audio isn't
+    /// supported).
+    pub sample_formats: &'static [SampleFormat],
+    /// The most audio channels supported.
+    pub max_channels: usize,
+    /// Whether seeking is supported.
+    pub seeking: bool,
+    /// Whether tags (`Metadata`) are supported.
+    pub metadata: bool,
+}
+
+impl Capabilities {
+    /// A codec that supports nothing, to build on with struct update syntax.
+    pub const NONE: Capabilities = Capabilities {
+        colors: &[],
+        max_wh: (0, 0),
+        animation: false,
+        sample_formats: &[],
+        max_channels: 0,
+        seeking: false,
+        metadata: false,
+    };
+
+    /// Return true if video is supported.
+    pub fn video(&self) -> bool {
+        !self.colors.is_empty()
+    }
+
+    /// Return true if audio is supported.
+    pub fn audio(&self) -> bool {
+        !self.sample_formats.is_empty()
+    }
+
+    /// Return true if any supported `ColorChannels` has alpha.
+    pub fn alpha(&self) -> bool {
+        self.colors.iter().any(|c| c.has_alpha())
+    }
+
+    /// Pick the supported `ColorChannels` to convert `format` to: `format`
+    /// itself if it's supported, otherwise the best one that keeps (or
+    /// doesn't add) alpha, and has the same number of channels if possible.
+    /// Returns `None` if video isn't supported.
+    pub fn pick_colors(&self, format: ColorChannels) -> Option<ColorChannels> {
+        if self.colors.contains(&format) {
+            return Some(format);
+        }
+        let rank = |c: &ColorChannels| {
+            (
+                c.has_alpha() == format.has_alpha(),
+                c.n_channels() == format.n_channels(),
+            )
+        };
+        let best = self.colors.iter().map(rank).max()?;
+        self.colors.iter().cloned().find(|c| rank(c) == best)
+    }
+
+    /// Pick the supported `SampleFormat` to convert `format` to: `format`
+    /// itself if it's supported, otherwise the smallest one that doesn't
+    /// lose precision, or the biggest one if they all do.  Returns `None` if
+    /// audio isn't supported.
+    pub fn pick_sample_format(&self, format: SampleFormat) -> Option<SampleFormat> {
+        let formats = self.sample_formats.iter().cloned();
+        let lossless =
+            |f: &SampleFormat| f.bits() >= format.bits() && (f.is_float() || !format.is_float());
+        formats
+            .clone()
+            .filter(lossless)
+            .min_by_key(|f| (f.bits(), *f != format))
+            .or_else(|| formats.max_by_key(|f| f.bits()))
+    }
+
+    /// Check that `video` can be encoded as it is.  Fails with
+    /// `Error::UnsupportedFeature` if not.
+    pub fn check_video(&self, video: &Video) -> Result<()> {
+        let (w, h) = video.wh();
+        if !self.colors.contains(&video.format()) {
+            Err(unsupported("video in this ColorChannels"))
+        } else if w > self.max_wh.0 || h > self.max_wh.1 {
+            Err(unsupported(&format!("video bigger than {:?}", self.max_wh)))
+        } else if !self.animation && video.frames() > 1 {
+            Err(unsupported("more than one video frame"))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Check that `audio` can be encoded as it is.  Fails with
+    /// `Error::UnsupportedFeature` if not.
+    pub fn check_audio(&self, audio: &Audio) -> Result<()> {
+        if audio.n_channels() > self.max_channels {
+            Err(unsupported(&format!(
+                "more than {} audio channels",
+                self.max_channels
+            )))
+        } else if audio
+            .iter()
+            .any(|f| !self.sample_formats.contains(&f.format()))
+        {
+            Err(unsupported("audio in this SampleFormat"))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+fn unsupported(what: &str) -> Error {
+    Error::UnsupportedFeature(what.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG: Capabilities = Capabilities {
+        colors: &[
+            ColorChannels::Srgba,
+            ColorChannels::Srgb,
+            ColorChannels::Sgrayscale,
+        ],
+        max_wh: (u16::MAX, u16::MAX),
+        animation: true,
+        metadata: true,
+        ..Capabilities::NONE
+    };
+
+    #[test]
+    fn pick() {
+        assert!(PNG.video() && PNG.alpha() && !PNG.audio());
+        assert_eq!(
+            PNG.pick_colors(ColorChannels::Srgb),
+            Some(ColorChannels::Srgb)
+        );
+        assert_eq!(
+            PNG.pick_colors(ColorChannels::Sbgra),
+            Some(ColorChannels::Srgba)
+        );
+        assert_eq!(
+            PNG.pick_colors(ColorChannels::Lbgr),
+            Some(ColorChannels::Srgb)
+        );
+        assert_eq!(PNG.pick_sample_format(SampleFormat::I16), None);
+
+        let flac = Capabilities {
+            sample_formats: &[SampleFormat::I16, SampleFormat::I24, SampleFormat::I8],
+            max_channels: 8,
+            ..Capabilities::NONE
+        };
+        assert_eq!(
+            flac.pick_sample_format(SampleFormat::I8),
+            Some(SampleFormat::I8)
+        );
+        assert_eq!(
+            flac.pick_sample_format(SampleFormat::I32),
+            Some(SampleFormat::I24)
+        );
+        assert_eq!(
+            flac.pick_sample_format(SampleFormat::F32),
+            Some(SampleFormat::I24)
+        );
+    }
+
+    #[test]
+    fn check() {
+        let video = Video::new(ColorChannels::Srgba, (640, 480), 2);
+        assert!(PNG.check_video(&video).is_ok());
+        let still = Capabilities {
+            animation: false,
+            ..PNG
+        };
+        assert!(still.check_video(&video).is_err());
+    }
+}
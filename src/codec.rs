@@ -2,54 +2,218 @@
 // Dual-licensed under either the MIT License or the Boost Software License, Version 1.0.
 // (See accompanying file LICENSE_1_0.txt or copy at https://www.boost.org/LICENSE_1_0.txt)
 
+use std::io::{Read, Seek, Write};
+
+use video::convert_u8;
 use Audio;
+use Capabilities;
 use ColorChannels;
+use Depth;
+use EncoderOption;
+use EncoderOptions;
+use Error;
+use Result;
+use StreamInfo;
+use Timebase;
+use Timestamp;
+use Tracks;
 use Video;
 
-/// Index for a frame.
-#[derive(Copy, Clone)]
-pub struct Index(pub u32);
+/// Index for a frame, at a frame rate.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Index {
+    /// The frame number.
+    pub frame: u32,
+    /// The frame rate.
+    pub timebase: Timebase,
+}
 /// A Video Frame
 #[derive(Clone)]
-pub struct VFrame(pub Vec<u8>);
-/// An audio frame (2000 samples = 1/24 of a second)
-#[derive(Clone)]
-pub struct AFrame(pub [i16; 2000]);
+pub struct VFrame {
+    /// The pixel data, with components stored at the `Video`'s `Depth`.
+    pub data: Vec<u8>,
+    /// Presentation timestamp, `None` if the frame is shown at its index.
+    pub pts: Option<Timestamp>,
+}
 
 impl Index {
+    /// Create a new frame index.
+    pub fn new(frame: u32, timebase: Timebase) -> Index {
+        Index { frame, timebase }
+    }
+
+    /// Get the index of the frame showing at a timestamp.
+    pub fn from_timestamp(ts: Timestamp, timebase: Timebase) -> Index {
+        Index::new(ts.to_index(timebase), timebase)
+    }
+
+    /// Get the index of the frame showing at a time in seconds.
+    pub fn from_seconds(seconds: f64, timebase: Timebase) -> Index {
+        // Round to the nearest frame, then step back if it starts after
+        // `seconds`, so that `to_seconds()` of a frame maps back to it.
+        let frame = timebase.from_seconds(seconds);
+        let frame = if timebase.to_seconds(frame) > seconds {
+            frame - 1
+        } else {
+            frame
+        };
+        Index::new(frame.clamp(0, u32::MAX as i64) as u32, timebase)
+    }
+
     /// Convert Index to seconds
-    pub fn to_seconds(self) -> f32 {
-        (self.0 as f32) / 24.0
+    pub fn to_seconds(self) -> f64 {
+        self.timebase.to_seconds(self.frame as i64)
+    }
+
+    /// Convert Index to the timestamp of the start of the frame.
+    pub fn to_timestamp(self) -> Timestamp {
+        Timestamp::new(self.frame as i64, self.timebase)
+    }
+}
+
+/// How to seek (see `Decoder::seek`).
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum SeekMode {
+    /// Go to exactly the frame asked for (decoding from the keyframe before
+    /// it, which can be slow).
+    Exact,
+    /// Go to the nearest keyframe at or before the frame asked for (or the
+    /// first keyframe, if there aren't any before).
+    KeyframeBefore,
+    /// Go to the nearest keyframe at or after the frame asked for (or the
+    /// last keyframe, if there aren't any after).
+    KeyframeAfter,
+}
+
+impl SeekMode {
+    /// Get the frame to go to, given the sorted `keyframes`.  If there
+    /// aren't any keyframes, every frame counts as one.
+    pub fn target(self, index: Index, keyframes: &[Index]) -> Index {
+        let frame = |k: &Index| k.to_timestamp().to_index(index.timebase);
+        let before = keyframes.iter().rev().find(|k| frame(k) <= index.frame);
+        let after = keyframes.iter().find(|k| frame(k) >= index.frame);
+        let target = match self {
+            SeekMode::Exact => None,
+            SeekMode::KeyframeBefore => before.or_else(|| keyframes.first()),
+            SeekMode::KeyframeAfter => after.or_else(|| keyframes.last()),
+        };
+        target.map_or(index, |k| Index::new(frame(k), index.timebase))
     }
 }
 
 impl VFrame {
-    /// Get color from index.
+    /// Create a new video frame without a presentation timestamp.
+    pub fn new(data: Vec<u8>) -> VFrame {
+        VFrame { data, pts: None }
+    }
+
+    /// Create a new video frame with a presentation timestamp.
+    pub fn with_pts(data: Vec<u8>, pts: Timestamp) -> VFrame {
+        VFrame {
+            data,
+            pts: Some(pts),
+        }
+    }
+
+    /// Get the presentation timestamp, falling back to `index` if the frame
+    /// doesn't have one.
+    pub fn pts_or(&self, index: Index) -> Timestamp {
+        self.pts.unwrap_or_else(|| index.to_timestamp())
+    }
+
+    /// Get color from index.  Only for `Depth::U8` frames (see `get_f32`).
     #[inline(always)]
     pub fn get(&self, mut index: usize) -> [u8; 4] {
         index *= 4;
         [
-            self.0[index + 0],
-            self.0[index + 1],
-            self.0[index + 2],
-            self.0[index + 3],
+            self.data[index],
+            self.data[index + 1],
+            self.data[index + 2],
+            self.data[index + 3],
         ]
     }
 
-    /// Set color with index.
+    /// Set color with index.  Only for `Depth::U8` frames (see `set_f32`).
     #[inline(always)]
     pub fn set(&mut self, mut index: usize, rgba: [u8; 4]) {
         index *= 4;
-        self.0[index + 0] = rgba[0];
-        self.0[index + 1] = rgba[1];
-        self.0[index + 2] = rgba[2];
-        self.0[index + 3] = rgba[3];
+        self.data[index] = rgba[0];
+        self.data[index + 1] = rgba[1];
+        self.data[index + 2] = rgba[2];
+        self.data[index + 3] = rgba[3];
+    }
+
+    /// Get color from index, for components stored at `depth` (see `get`).
+    pub fn get_f32(&self, depth: Depth, index: usize) -> [f32; 4] {
+        let i = index * 4;
+        [
+            depth.get(&self.data, i),
+            depth.get(&self.data, i + 1),
+            depth.get(&self.data, i + 2),
+            depth.get(&self.data, i + 3),
+        ]
+    }
+
+    /// Set color with index, for components stored at `depth` (see `set`).
+    pub fn set_f32(&mut self, depth: Depth, index: usize, rgba: [f32; 4]) {
+        for (i, c) in rgba.iter().enumerate() {
+            depth.set(&mut self.data, index * 4 + i, *c);
+        }
+    }
+
+    /// Convert the frame's components from one `Depth` to another.
+    pub fn to_depth(&self, from: Depth, to: Depth) -> VFrame {
+        VFrame {
+            data: to.from(from, &self.data),
+            pts: self.pts,
+        }
+    }
+
+    /// Convert the frame's pixels from one `ColorChannels` to another, for
+    /// components stored at `depth`.  Each pixel has `n_channels()`
+    /// components, so the frame grows or shrinks to fit.
+    pub fn to_format(&self, from: ColorChannels, to: ColorChannels, depth: Depth) -> VFrame {
+        if from == to {
+            return self.clone();
+        }
+        let (n_from, n_to) = (from.n_channels(), to.n_channels());
+        let len = self.data.len() / (n_from * depth.bytes());
+        let mut data = vec![0; len * n_to * depth.bytes()];
+        if depth == Depth::U8 {
+            if convert_u8(from, to, &self.data, &mut data) {
+                return VFrame {
+                    data,
+                    pts: self.pts,
+                };
+            }
+            for (i, src) in self.data.chunks_exact(n_from).enumerate() {
+                let mut p = [0, 0, 0, 255];
+                p[..n_from].copy_from_slice(src);
+                let p = to.from(from, p);
+                data[i * n_to..(i + 1) * n_to].copy_from_slice(&p[..n_to]);
+            }
+        } else {
+            for i in 0..len {
+                let mut p = [0.0, 0.0, 0.0, 1.0];
+                for (c, v) in p.iter_mut().enumerate().take(n_from) {
+                    *v = depth.get(&self.data, i * n_from + c);
+                }
+                let p = to.from_f32(from, depth, p);
+                for (c, v) in p.iter().enumerate().take(n_to) {
+                    depth.set(&mut data, i * n_to + c, *v);
+                }
+            }
+        }
+        VFrame {
+            data,
+            pts: self.pts,
+        }
     }
 
     /// Clear the VFrame.
     #[inline(always)]
     pub fn clear(&mut self) {
-        for i in &mut self.0 {
+        for i in &mut self.data {
             *i = 0;
         }
     }
@@ -60,7 +224,7 @@ impl VFrame {
         let channels = format.n_channels();
 
         for i in 0..channels {
-            rgba[i] = self.0[index * channels + i];
+            rgba[i] = self.data[index * channels + i];
         }
 
         rgba
@@ -73,7 +237,7 @@ impl VFrame {
         let channels = format.n_channels();
 
         for i in 0..channels {
-            self.0[index * channels + i] = rgba[i];
+            self.data[index * channels + i] = rgba[i];
         }
     }*/
 }
@@ -83,12 +247,24 @@ pub trait EncoderV
 where
     Self: Sized,
 {
-    /// Create a new encoder for this video.
-    fn new(video: &Video) -> Self;
-    /// Encode a frame (24fps) and return appended data.
-    fn run(&mut self, frame: &VFrame) -> Vec<u8>;
+    /// What the encoder can do.
+    const CAPABILITIES: Capabilities;
+    /// The options that `with_options` supports.
+    const OPTIONS: &'static [EncoderOption] = &[];
+    /// Create a new encoder for this video.  Formats that support tags
+    /// should write `video.metadata`.  Fails with
+    /// `Error::UnsupportedFeature` if the format can't store this video.
+    fn new(video: &Video) -> Result<Self>;
+    /// Create a new encoder for this video, with options.  The default
+    /// rejects any options, so encoders that set `OPTIONS` must override it.
+    fn with_options(video: &Video, options: &EncoderOptions) -> Result<Self> {
+        options.check(&[])?;
+        Self::new(video)
+    }
+    /// Encode a frame and return appended data.
+    fn run(&mut self, frame: &VFrame) -> Result<Vec<u8>>;
     /// Finish the encoding and return appended data.
-    fn end(self) -> Vec<u8>;
+    fn end(self) -> Result<Vec<u8>>;
 }
 
 /// A trait for implementing encoding audio.
@@ -96,12 +272,24 @@ pub trait EncoderA
 where
     Self: Sized,
 {
-    /// Create a new encoder for this audio.
-    fn new(audio: &Audio) -> Self;
-    /// Encode a frame (2000 samples / 24fps) and return appended data.
-    fn run(&mut self, audio: &mut Audio) -> Vec<u8>;
+    /// What the encoder can do.
+    const CAPABILITIES: Capabilities;
+    /// The options that `with_options` supports.
+    const OPTIONS: &'static [EncoderOption] = &[];
+    /// Create a new encoder for this audio.  Formats that support tags
+    /// should write `audio.metadata`.  Fails with
+    /// `Error::UnsupportedFeature` if the format can't store this audio.
+    fn new(audio: &Audio) -> Result<Self>;
+    /// Create a new encoder for this audio, with options.  The default
+    /// rejects any options, so encoders that set `OPTIONS` must override it.
+    fn with_options(audio: &Audio, options: &EncoderOptions) -> Result<Self> {
+        options.check(&[])?;
+        Self::new(audio)
+    }
+    /// Encode a frame and return appended data.
+    fn run(&mut self, audio: &mut Audio) -> Result<Vec<u8>>;
     /// Finish the encoding and return appended data.
-    fn end(self) -> Vec<u8>;
+    fn end(self) -> Result<Vec<u8>>;
 }
 
 /// A trait for implementing encoding audio and video together.
@@ -109,29 +297,435 @@ pub trait EncoderAV
 where
     Self: Sized,
 {
+    /// What the encoder can do.
+    const CAPABILITIES: Capabilities;
+    /// The options that `with_options` supports.
+    const OPTIONS: &'static [EncoderOption] = &[];
     /// Create a new encoder for this audiovideo
-    fn new(video: &Video, audio: &Audio) -> Self;
-    /// Encode a frame (2000 samples / 24fps) and return appended data.
-    fn run(&mut self, audio: &mut Audio, video: &mut Video) -> Vec<u8>;
+    fn new(video: &Video, audio: &Audio) -> Result<Self>;
+    /// Create a new encoder for this audiovideo, with options.  The default
+    /// rejects any options, so encoders that set `OPTIONS` must override it.
+    fn with_options(video: &Video, audio: &Audio, options: &EncoderOptions) -> Result<Self> {
+        options.check(&[])?;
+        Self::new(video, audio)
+    }
+    /// Encode a frame and return appended data.
+    fn run(&mut self, audio: &mut Audio, video: &mut Video) -> Result<Vec<u8>>;
+    /// Finish the encoding and return appended data.
+    fn end(self) -> Result<Vec<u8>>;
+}
+
+/// A trait for implementing encoding any number of video, audio and subtitle
+/// tracks together, like `EncoderAV` for one of each.
+pub trait EncoderTracks
+where
+    Self: Sized,
+{
+    /// What the encoder can do.
+    const CAPABILITIES: Capabilities;
+    /// The options that `with_options` supports.
+    const OPTIONS: &'static [EncoderOption] = &[];
+    /// Create a new encoder for these tracks (`info.tracks`) and tags.
+    fn new(info: &StreamInfo) -> Result<Self>;
+    /// Create a new encoder for these tracks, with options (see
+    /// `EncoderV::with_options`).
+    fn with_options(info: &StreamInfo, options: &EncoderOptions) -> Result<Self> {
+        options.check(&[])?;
+        Self::new(info)
+    }
+    /// Encode a frame of each track and return appended data.
+    fn run(&mut self, tracks: &mut Tracks) -> Result<Vec<u8>>;
     /// Finish the encoding and return appended data.
-    fn end(self) -> Vec<u8>;
+    fn end(self) -> Result<Vec<u8>>;
+}
+
+/// A trait for implementing encoding video straight into a `Write` sink,
+/// without allocating a `Vec` for every frame.
+pub trait StreamEncoderV<W: Write>
+where
+    Self: Sized,
+{
+    /// What the encoder can do.
+    const CAPABILITIES: Capabilities;
+    /// The options that `with_options` supports.
+    const OPTIONS: &'static [EncoderOption] = &[];
+    /// Create a new encoder for this video, writing to `output`.
+    fn new(video: &Video, output: W) -> Result<Self>;
+    /// Create a new encoder for this video with options, writing to
+    /// `output` (see `EncoderV::with_options`).
+    fn with_options(video: &Video, options: &EncoderOptions, output: W) -> Result<Self> {
+        options.check(&[])?;
+        Self::new(video, output)
+    }
+    /// Encode a frame.
+    fn run(&mut self, frame: &VFrame) -> Result<()>;
+    /// Finish the encoding, and return the output.
+    fn end(self) -> Result<W>;
+}
+
+/// A trait for implementing encoding audio straight into a `Write` sink,
+/// without allocating a `Vec` for every frame.
+pub trait StreamEncoderA<W: Write>
+where
+    Self: Sized,
+{
+    /// What the encoder can do.
+    const CAPABILITIES: Capabilities;
+    /// The options that `with_options` supports.
+    const OPTIONS: &'static [EncoderOption] = &[];
+    /// Create a new encoder for this audio, writing to `output`.
+    fn new(audio: &Audio, output: W) -> Result<Self>;
+    /// Create a new encoder for this audio with options, writing to
+    /// `output` (see `EncoderA::with_options`).
+    fn with_options(audio: &Audio, options: &EncoderOptions, output: W) -> Result<Self> {
+        options.check(&[])?;
+        Self::new(audio, output)
+    }
+    /// Encode a frame.
+    fn run(&mut self, audio: &mut Audio) -> Result<()>;
+    /// Finish the encoding, and return the output.
+    fn end(self) -> Result<W>;
+}
+
+/// A trait for implementing encoding audio and video together straight
+/// into a `Write` sink, without allocating a `Vec` for every frame.
+pub trait StreamEncoderAV<W: Write>
+where
+    Self: Sized,
+{
+    /// What the encoder can do.
+    const CAPABILITIES: Capabilities;
+    /// The options that `with_options` supports.
+    const OPTIONS: &'static [EncoderOption] = &[];
+    /// Create a new encoder for this audiovideo, writing to `output`.
+    fn new(video: &Video, audio: &Audio, output: W) -> Result<Self>;
+    /// Create a new encoder for this audiovideo with options, writing to
+    /// `output` (see `EncoderAV::with_options`).
+    fn with_options(
+        video: &Video,
+        audio: &Audio,
+        options: &EncoderOptions,
+        output: W,
+    ) -> Result<Self> {
+        options.check(&[])?;
+        Self::new(video, audio, output)
+    }
+    /// Encode a frame.
+    fn run(&mut self, audio: &mut Audio, video: &mut Video) -> Result<()>;
+    /// Finish the encoding, and return the output.
+    fn end(self) -> Result<W>;
 }
 
-/// A trait for implementing decoding audio, video or both.
+/// Use an `EncoderV`, `EncoderA` or `EncoderAV` as a `StreamEncoderV`,
+/// `StreamEncoderA` or `StreamEncoderAV`, writing each returned chunk to
+/// the output.
+pub struct WriteAdapter<E, W> {
+    encoder: E,
+    output: W,
+}
+
+impl<E, W> WriteAdapter<E, W> {
+    /// Get the wrapped encoder.
+    pub fn encoder(&self) -> &E {
+        &self.encoder
+    }
+
+    /// Get the output.
+    pub fn output(&self) -> &W {
+        &self.output
+    }
+}
+
+impl<E: EncoderV, W: Write> StreamEncoderV<W> for WriteAdapter<E, W> {
+    const CAPABILITIES: Capabilities = E::CAPABILITIES;
+    const OPTIONS: &'static [EncoderOption] = E::OPTIONS;
+
+    fn new(video: &Video, output: W) -> Result<Self> {
+        let encoder = E::new(video)?;
+        Ok(WriteAdapter { encoder, output })
+    }
+
+    fn with_options(video: &Video, options: &EncoderOptions, output: W) -> Result<Self> {
+        let encoder = E::with_options(video, options)?;
+        Ok(WriteAdapter { encoder, output })
+    }
+
+    fn run(&mut self, frame: &VFrame) -> Result<()> {
+        let data = self.encoder.run(frame)?;
+        Ok(self.output.write_all(&data)?)
+    }
+
+    fn end(mut self) -> Result<W> {
+        let data = self.encoder.end()?;
+        self.output.write_all(&data)?;
+        self.output.flush()?;
+        Ok(self.output)
+    }
+}
+
+impl<E: EncoderA, W: Write> StreamEncoderA<W> for WriteAdapter<E, W> {
+    const CAPABILITIES: Capabilities = E::CAPABILITIES;
+    const OPTIONS: &'static [EncoderOption] = E::OPTIONS;
+
+    fn new(audio: &Audio, output: W) -> Result<Self> {
+        let encoder = E::new(audio)?;
+        Ok(WriteAdapter { encoder, output })
+    }
+
+    fn with_options(audio: &Audio, options: &EncoderOptions, output: W) -> Result<Self> {
+        let encoder = E::with_options(audio, options)?;
+        Ok(WriteAdapter { encoder, output })
+    }
+
+    fn run(&mut self, audio: &mut Audio) -> Result<()> {
+        let data = self.encoder.run(audio)?;
+        Ok(self.output.write_all(&data)?)
+    }
+
+    fn end(mut self) -> Result<W> {
+        let data = self.encoder.end()?;
+        self.output.write_all(&data)?;
+        self.output.flush()?;
+        Ok(self.output)
+    }
+}
+
+impl<E: EncoderAV, W: Write> StreamEncoderAV<W> for WriteAdapter<E, W> {
+    const CAPABILITIES: Capabilities = E::CAPABILITIES;
+    const OPTIONS: &'static [EncoderOption] = E::OPTIONS;
+
+    fn new(video: &Video, audio: &Audio, output: W) -> Result<Self> {
+        let encoder = E::new(video, audio)?;
+        Ok(WriteAdapter { encoder, output })
+    }
+
+    fn with_options(
+        video: &Video,
+        audio: &Audio,
+        options: &EncoderOptions,
+        output: W,
+    ) -> Result<Self> {
+        let encoder = E::with_options(video, audio, options)?;
+        Ok(WriteAdapter { encoder, output })
+    }
+
+    fn run(&mut self, audio: &mut Audio, video: &mut Video) -> Result<()> {
+        let data = self.encoder.run(audio, video)?;
+        Ok(self.output.write_all(&data)?)
+    }
+
+    fn end(mut self) -> Result<W> {
+        let data = self.encoder.end()?;
+        self.output.write_all(&data)?;
+        self.output.flush()?;
+        Ok(self.output)
+    }
+}
+
+/// A trait for implementing decoding audio, video or both from data that's
+/// all in memory.  See `StreamDecoder` for large files and pipes.
 pub trait Decoder<T>
 where
     Self: Sized,
 {
-    /// Create a new decoder for this format.  `None` is returned when the
-    /// decoder can't handle `data`'s format: try a different decoder.
-    fn new(data: T, colors: ColorChannels) -> Option<Self>;
-    /// Decode a frame.  `None` is returned if the file is corrupt,
-    /// `Some(true)` if it succeeded, and `Some(false)` if it can't add
-    /// anymore frames because the input file ended.  `audio` and `video`
-    /// should initially be set to `None`.
-    fn run(&mut self, audio: &mut Option<Audio>, video: &mut Option<Video>) -> Option<bool>;
-    /// Get the frame number (24 frames per second).
+    /// What the decoder can do.
+    const CAPABILITIES: Capabilities;
+    /// Create a new decoder for this format.  `Error::UnsupportedFormat` is
+    /// returned when the decoder can't handle `data`'s format: try a
+    /// different decoder.
+    fn new(data: T, colors: ColorChannels) -> Result<Self>;
+    /// Get what's in the file (dimensions, frame rate, sample rate, tags,
+    /// etc.), which is known without decoding frames.  The default says
+    /// nothing is known.
+    fn info(&self) -> StreamInfo {
+        StreamInfo::default()
+    }
+    /// Decode a frame.  `Ok(true)` is returned if it succeeded, and
+    /// `Ok(false)` if it can't add anymore frames because the input file
+    /// ended.  `audio` and `video` should initially be set to `None`.  Tags
+    /// should be copied into their `metadata` when they are created.
+    fn run(&mut self, audio: &mut Option<Audio>, video: &mut Option<Video>) -> Result<bool>;
+    /// Choose the tracks (by id, see `StreamInfo::tracks`) that
+    /// `run_tracks` decodes.  The default fails with
+    /// `Error::UnsupportedFeature`.
+    fn select(&mut self, tracks: &[u32]) -> Result<()> {
+        let _ = tracks;
+        Err(Error::UnsupportedFeature("selecting tracks".to_string()))
+    }
+    /// Decode a frame of each selected track into `tracks`, returning like
+    /// `run`.  The default uses `run`, for the first video and audio tracks.
+    fn run_tracks(&mut self, tracks: &mut Tracks) -> Result<bool> {
+        let info = self.info();
+        tracks.run_single(&info, |audio, video| self.run(audio, video))
+    }
+    /// Get the frame number, at the video's frame rate.
     fn get(&self) -> Index;
     /// Set the frame number to seek forward or backwards.
     fn set(&mut self, index: Index);
+    /// Get the keyframes (frames that can be decoded without decoding the
+    /// frames before them), sorted.  `None` if they aren't known.
+    fn keyframes(&self) -> Option<Vec<Index>> {
+        None
+    }
+    /// Seek to a frame, and return the frame that it went to.  The default
+    /// uses `keyframes` and `set`.
+    fn seek(&mut self, index: Index, mode: SeekMode) -> Result<Index> {
+        let target = mode.target(index, &self.keyframes().unwrap_or_default());
+        self.set(target);
+        Ok(self.get())
+    }
+}
+
+/// A trait for implementing decoding audio, video or both from a stream,
+/// pulling in bytes as they're needed (see `Input`), so memory use doesn't
+/// grow with the size of the file.
+pub trait StreamDecoder<R: Read>
+where
+    Self: Sized,
+{
+    /// What the decoder can do.
+    const CAPABILITIES: Capabilities;
+    /// Create a new decoder, reading the headers from `input`.
+    /// `Error::UnsupportedFormat` is returned when the decoder can't handle
+    /// the format: try a different decoder.
+    fn new(input: R, colors: ColorChannels) -> Result<Self>;
+    /// Get what's in the file (dimensions, frame rate, sample rate, tags,
+    /// etc.), which is known without decoding frames.  The default says
+    /// nothing is known.
+    fn info(&self) -> StreamInfo {
+        StreamInfo::default()
+    }
+    /// Decode a frame (see `Decoder::run`).
+    fn run(&mut self, audio: &mut Option<Audio>, video: &mut Option<Video>) -> Result<bool>;
+    /// Choose the tracks to decode (see `Decoder::select`).
+    fn select(&mut self, tracks: &[u32]) -> Result<()> {
+        let _ = tracks;
+        Err(Error::UnsupportedFeature("selecting tracks".to_string()))
+    }
+    /// Decode a frame of each selected track (see `Decoder::run_tracks`).
+    fn run_tracks(&mut self, tracks: &mut Tracks) -> Result<bool> {
+        let info = self.info();
+        tracks.run_single(&info, |audio, video| self.run(audio, video))
+    }
+    /// Get the frame number, at the video's frame rate.
+    fn get(&self) -> Index;
+}
+
+/// A `StreamDecoder` that can seek, because its input can.
+pub trait SeekDecoder<R: Read + Seek>: StreamDecoder<R> {
+    /// Set the frame number to seek forward or backwards.
+    fn set(&mut self, index: Index) -> Result<()>;
+    /// Get the keyframes (see `Decoder::keyframes`).
+    fn keyframes(&self) -> Option<Vec<Index>> {
+        None
+    }
+    /// Seek to a frame, and return the frame that it went to (see
+    /// `Decoder::seek`).
+    fn seek(&mut self, index: Index, mode: SeekMode) -> Result<Index> {
+        let target = mode.target(index, &self.keyframes().unwrap_or_default());
+        self.set(target)?;
+        Ok(self.get())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_from_seconds() {
+        for &timebase in &[
+            Timebase::FPS_23_976,
+            Timebase::FPS_29_97,
+            Timebase::FPS_59_94,
+        ] {
+            for frame in (0..200_000).step_by(997) {
+                let secs = Index::new(frame, timebase).to_seconds();
+                assert_eq!(Index::from_seconds(secs, timebase).frame, frame);
+                let before = Index::from_seconds(secs - 1e-9, timebase).frame;
+                assert_eq!(before, frame.saturating_sub(1));
+            }
+        }
+        assert_eq!(Index::from_seconds(-1.0, Timebase::FPS_24).frame, 0);
+        assert_eq!(Index::from_seconds(0.99 / 24.0, Timebase::FPS_24).frame, 0);
+    }
+
+    /// An "encoder" that returns the raw frames, then a trailer.
+    struct Raw;
+
+    impl EncoderV for Raw {
+        const CAPABILITIES: Capabilities = Capabilities {
+            colors: &[ColorChannels::Srgba],
+            max_wh: (u16::MAX, u16::MAX),
+            animation: true,
+            ..Capabilities::NONE
+        };
+        // Without overriding `with_options`, so these can't be used.
+        const OPTIONS: &'static [EncoderOption] = &[EncoderOption::Quality];
+
+        fn new(_video: &Video) -> Result<Self> {
+            Ok(Raw)
+        }
+
+        fn run(&mut self, frame: &VFrame) -> Result<Vec<u8>> {
+            Ok(frame.data.clone())
+        }
+
+        fn end(self) -> Result<Vec<u8>> {
+            Ok(b"END".to_vec())
+        }
+    }
+
+    #[test]
+    fn seek_target() {
+        let fps = Timebase::FPS_24;
+        let keyframes = [Index::new(0, fps), Index::new(12, fps), Index::new(24, fps)];
+        let at = |frame, mode: SeekMode| mode.target(Index::new(frame, fps), &keyframes).frame;
+        assert_eq!(at(13, SeekMode::Exact), 13);
+        assert_eq!(at(13, SeekMode::KeyframeBefore), 12);
+        assert_eq!(at(13, SeekMode::KeyframeAfter), 24);
+        assert_eq!(at(12, SeekMode::KeyframeAfter), 12);
+        assert_eq!(at(30, SeekMode::KeyframeAfter), 24);
+        // Falls back to the first keyframe if none are before.
+        let keyframes = [Index::new(6, fps), Index::new(12, fps)];
+        let at = |frame, mode: SeekMode| mode.target(Index::new(frame, fps), &keyframes).frame;
+        assert_eq!(at(3, SeekMode::KeyframeBefore), 6);
+        assert_eq!(at(3, SeekMode::Exact), 3);
+        // Keyframes in another timebase (every half second).
+        let keyframes = [
+            Index::new(0, Timebase::new(2, 1)),
+            Index::new(1, Timebase::new(2, 1)),
+        ];
+        let index = Index::new(13, fps);
+        assert_eq!(
+            SeekMode::KeyframeBefore.target(index, &keyframes),
+            Index::new(12, fps)
+        );
+    }
+
+    #[test]
+    fn write_adapter() {
+        let video = Video::new(ColorChannels::Srgba, (1, 1), 2);
+        let mut encoder: WriteAdapter<Raw, Vec<u8>> =
+            StreamEncoderV::new(&video, Vec::new()).unwrap();
+        encoder.run(&VFrame::new(vec![1, 2, 3, 4])).unwrap();
+        encoder.run(&VFrame::new(vec![5, 6, 7, 8])).unwrap();
+        assert_eq!(encoder.output().len(), 8);
+        assert_eq!(
+            encoder.end().unwrap(),
+            b"\x01\x02\x03\x04\x05\x06\x07\x08END"
+        );
+    }
+
+    #[test]
+    fn options_not_ignored() {
+        let video = Video::new(ColorChannels::Srgba, (1, 1), 2);
+        let mut options = EncoderOptions::new();
+        assert!(Raw::with_options(&video, &options).is_ok());
+        options.quality = Some(0.5);
+        assert!(matches!(
+            Raw::with_options(&video, &options),
+            Err(Error::InvalidOption(_))
+        ));
+    }
 }
@@ -0,0 +1,526 @@
+// Copyright Jeron Lau 2017 - 2018.
+// Dual-licensed under either the MIT License or the Boost Software License, Version 1.0.
+// (See accompanying file LICENSE_1_0.txt or copy at https://www.boost.org/LICENSE_1_0.txt)
+
+use std::io::{Read, Seek};
+use std::marker::PhantomData;
+
+use Audio;
+use Capabilities;
+use ColorChannels;
+use Decoder;
+use EncoderA;
+use EncoderAV;
+use EncoderOption;
+use EncoderOptions;
+use EncoderV;
+use Error;
+use Index;
+use Result;
+use SeekDecoder;
+use SeekMode;
+use StreamDecoder;
+use StreamInfo;
+use Tracks;
+use VFrame;
+use Video;
+
+/// Input that can be read and seeked, for `DecoderFactory::create_seekable`.
+pub trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek> ReadSeek for T {}
+
+/// An object-safe `EncoderV`, implemented for every `EncoderV`.
+pub trait DynEncoderV {
+    /// Encode a frame and return appended data.
+    fn run(&mut self, frame: &VFrame) -> Result<Vec<u8>>;
+    /// Finish the encoding and return appended data.
+    fn end(self: Box<Self>) -> Result<Vec<u8>>;
+}
+
+impl<E: EncoderV> DynEncoderV for E {
+    fn run(&mut self, frame: &VFrame) -> Result<Vec<u8>> {
+        EncoderV::run(self, frame)
+    }
+
+    fn end(self: Box<Self>) -> Result<Vec<u8>> {
+        EncoderV::end(*self)
+    }
+}
+
+/// An object-safe `EncoderA`, implemented for every `EncoderA`.
+pub trait DynEncoderA {
+    /// Encode a frame and return appended data.
+    fn run(&mut self, audio: &mut Audio) -> Result<Vec<u8>>;
+    /// Finish the encoding and return appended data.
+    fn end(self: Box<Self>) -> Result<Vec<u8>>;
+}
+
+impl<E: EncoderA> DynEncoderA for E {
+    fn run(&mut self, audio: &mut Audio) -> Result<Vec<u8>> {
+        EncoderA::run(self, audio)
+    }
+
+    fn end(self: Box<Self>) -> Result<Vec<u8>> {
+        EncoderA::end(*self)
+    }
+}
+
+/// An object-safe `EncoderAV`, implemented for every `EncoderAV`.
+pub trait DynEncoderAV {
+    /// Encode a frame and return appended data.
+    fn run(&mut self, audio: &mut Audio, video: &mut Video) -> Result<Vec<u8>>;
+    /// Finish the encoding and return appended data.
+    fn end(self: Box<Self>) -> Result<Vec<u8>>;
+}
+
+impl<E: EncoderAV> DynEncoderAV for E {
+    fn run(&mut self, audio: &mut Audio, video: &mut Video) -> Result<Vec<u8>> {
+        EncoderAV::run(self, audio, video)
+    }
+
+    fn end(self: Box<Self>) -> Result<Vec<u8>> {
+        EncoderAV::end(*self)
+    }
+}
+
+/// An object-safe decoder, created by a `DecoderFactory`.
+pub trait DynDecoder {
+    /// Get what's in the file (see `Decoder::info`).
+    fn info(&self) -> StreamInfo;
+    /// Decode a frame (see `Decoder::run`).
+    fn run(&mut self, audio: &mut Option<Audio>, video: &mut Option<Video>) -> Result<bool>;
+    /// Choose the tracks to decode (see `Decoder::select`).
+    fn select(&mut self, tracks: &[u32]) -> Result<()>;
+    /// Decode a frame of each selected track (see `Decoder::run_tracks`).
+    fn run_tracks(&mut self, tracks: &mut Tracks) -> Result<bool>;
+    /// Get the frame number, at the video's frame rate.
+    fn get(&self) -> Index;
+    /// Set the frame number to seek forward or backwards.
+    fn set(&mut self, index: Index) -> Result<()>;
+    /// Get the keyframes (see `Decoder::keyframes`).
+    fn keyframes(&self) -> Option<Vec<Index>>;
+    /// Seek to a frame, and return the frame that it went to (see
+    /// `Decoder::seek`).
+    fn seek(&mut self, index: Index, mode: SeekMode) -> Result<Index>;
+}
+
+/// Creates `DynEncoderV`s, to choose a video encoder at runtime.
+pub trait EncoderVFactory {
+    /// Get what the encoder can do.
+    fn capabilities(&self) -> Capabilities;
+    /// Get the options that `create` supports.
+    fn options(&self) -> &'static [EncoderOption];
+    /// Create a new encoder for this video (see `EncoderV::with_options`).
+    fn create(&self, video: &Video, options: &EncoderOptions) -> Result<Box<dyn DynEncoderV>>;
+}
+
+/// Creates `DynEncoderA`s, to choose an audio encoder at runtime.
+pub trait EncoderAFactory {
+    /// Get what the encoder can do.
+    fn capabilities(&self) -> Capabilities;
+    /// Get the options that `create` supports.
+    fn options(&self) -> &'static [EncoderOption];
+    /// Create a new encoder for this audio (see `EncoderA::with_options`).
+    fn create(&self, audio: &Audio, options: &EncoderOptions) -> Result<Box<dyn DynEncoderA>>;
+}
+
+/// Creates `DynEncoderAV`s, to choose an audio and video encoder at runtime.
+pub trait EncoderAVFactory {
+    /// Get what the encoder can do.
+    fn capabilities(&self) -> Capabilities;
+    /// Get the options that `create` supports.
+    fn options(&self) -> &'static [EncoderOption];
+    /// Create a new encoder for this audiovideo (see
+    /// `EncoderAV::with_options`).
+    fn create(
+        &self,
+        video: &Video,
+        audio: &Audio,
+        options: &EncoderOptions,
+    ) -> Result<Box<dyn DynEncoderAV>>;
+}
+
+/// Creates `DynDecoder`s, to choose a decoder at runtime.
+pub trait DecoderFactory {
+    /// Get what the decoder can do.
+    fn capabilities(&self) -> Capabilities;
+    /// Create a new decoder for `input`, which doesn't need to be seekable
+    /// (a pipe or socket).  Pass an `Input` that `Registry::probe_input`
+    /// peeked at, so that the peeked bytes are decoded too.
+    fn create(&self, input: Box<dyn Read>, colors: ColorChannels) -> Result<Box<dyn DynDecoder>>;
+    /// Create a new decoder for seekable `input`.  The default uses
+    /// `create`.
+    fn create_seekable(
+        &self,
+        input: Box<dyn ReadSeek>,
+        colors: ColorChannels,
+    ) -> Result<Box<dyn DynDecoder>> {
+        self.create(Box::new(input), colors)
+    }
+}
+
+/// The factory for an `EncoderV`, `EncoderA`, `EncoderAV` or an in-memory
+/// `Decoder<Vec<u8>>` (which reads all of its input first, so probe it
+/// before creating the decoder).  Use `StreamFactory` for `StreamDecoder`s,
+/// and `SeekFactory` for `SeekDecoder`s.
+pub struct Factory<C>(PhantomData<fn() -> C>);
+
+impl<C> Factory<C> {
+    /// Create the factory.
+    pub fn new() -> Factory<C> {
+        Factory(PhantomData)
+    }
+}
+
+impl<C> Default for Factory<C> {
+    fn default() -> Self {
+        Factory::new()
+    }
+}
+
+impl<E: EncoderV + 'static> EncoderVFactory for Factory<E> {
+    fn capabilities(&self) -> Capabilities {
+        E::CAPABILITIES
+    }
+
+    fn options(&self) -> &'static [EncoderOption] {
+        E::OPTIONS
+    }
+
+    fn create(&self, video: &Video, options: &EncoderOptions) -> Result<Box<dyn DynEncoderV>> {
+        Ok(Box::new(E::with_options(video, options)?))
+    }
+}
+
+impl<E: EncoderA + 'static> EncoderAFactory for Factory<E> {
+    fn capabilities(&self) -> Capabilities {
+        E::CAPABILITIES
+    }
+
+    fn options(&self) -> &'static [EncoderOption] {
+        E::OPTIONS
+    }
+
+    fn create(&self, audio: &Audio, options: &EncoderOptions) -> Result<Box<dyn DynEncoderA>> {
+        Ok(Box::new(E::with_options(audio, options)?))
+    }
+}
+
+impl<E: EncoderAV + 'static> EncoderAVFactory for Factory<E> {
+    fn capabilities(&self) -> Capabilities {
+        E::CAPABILITIES
+    }
+
+    fn options(&self) -> &'static [EncoderOption] {
+        E::OPTIONS
+    }
+
+    fn create(
+        &self,
+        video: &Video,
+        audio: &Audio,
+        options: &EncoderOptions,
+    ) -> Result<Box<dyn DynEncoderAV>> {
+        Ok(Box::new(E::with_options(video, audio, options)?))
+    }
+}
+
+/// A `Decoder<Vec<u8>>` as a `DynDecoder`.
+struct Memory<D>(D);
+
+impl<D: Decoder<Vec<u8>>> DynDecoder for Memory<D> {
+    fn info(&self) -> StreamInfo {
+        self.0.info()
+    }
+
+    fn run(&mut self, audio: &mut Option<Audio>, video: &mut Option<Video>) -> Result<bool> {
+        self.0.run(audio, video)
+    }
+
+    fn select(&mut self, tracks: &[u32]) -> Result<()> {
+        self.0.select(tracks)
+    }
+
+    fn run_tracks(&mut self, tracks: &mut Tracks) -> Result<bool> {
+        self.0.run_tracks(tracks)
+    }
+
+    fn get(&self) -> Index {
+        self.0.get()
+    }
+
+    fn set(&mut self, index: Index) -> Result<()> {
+        self.0.set(index);
+        Ok(())
+    }
+
+    fn keyframes(&self) -> Option<Vec<Index>> {
+        self.0.keyframes()
+    }
+
+    fn seek(&mut self, index: Index, mode: SeekMode) -> Result<Index> {
+        self.0.seek(index, mode)
+    }
+}
+
+impl<D: Decoder<Vec<u8>> + 'static> DecoderFactory for Factory<D> {
+    fn capabilities(&self) -> Capabilities {
+        D::CAPABILITIES
+    }
+
+    fn create(
+        &self,
+        mut input: Box<dyn Read>,
+        colors: ColorChannels,
+    ) -> Result<Box<dyn DynDecoder>> {
+        let mut data = Vec::new();
+        input.read_to_end(&mut data)?;
+        Ok(Box::new(Memory(D::new(data, colors)?)))
+    }
+}
+
+/// The factory for a `StreamDecoder`, which reads its input as it goes.
+/// Its decoders can't seek.
+pub struct StreamFactory<D>(PhantomData<fn() -> D>);
+
+impl<D> StreamFactory<D> {
+    /// Create the factory.
+    pub fn new() -> StreamFactory<D> {
+        StreamFactory(PhantomData)
+    }
+}
+
+impl<D> Default for StreamFactory<D> {
+    fn default() -> Self {
+        StreamFactory::new()
+    }
+}
+
+/// A `StreamDecoder` as a `DynDecoder`.
+struct Stream<D>(D);
+
+impl<D: StreamDecoder<Box<dyn Read>>> DynDecoder for Stream<D> {
+    fn info(&self) -> StreamInfo {
+        self.0.info()
+    }
+
+    fn run(&mut self, audio: &mut Option<Audio>, video: &mut Option<Video>) -> Result<bool> {
+        self.0.run(audio, video)
+    }
+
+    fn select(&mut self, tracks: &[u32]) -> Result<()> {
+        self.0.select(tracks)
+    }
+
+    fn run_tracks(&mut self, tracks: &mut Tracks) -> Result<bool> {
+        self.0.run_tracks(tracks)
+    }
+
+    fn get(&self) -> Index {
+        self.0.get()
+    }
+
+    fn set(&mut self, _index: Index) -> Result<()> {
+        Err(Error::UnsupportedFeature("seeking".to_string()))
+    }
+
+    fn keyframes(&self) -> Option<Vec<Index>> {
+        None
+    }
+
+    fn seek(&mut self, _index: Index, _mode: SeekMode) -> Result<Index> {
+        Err(Error::UnsupportedFeature("seeking".to_string()))
+    }
+}
+
+impl<D: StreamDecoder<Box<dyn Read>> + 'static> DecoderFactory for StreamFactory<D> {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            seeking: false,
+            ..D::CAPABILITIES
+        }
+    }
+
+    fn create(&self, input: Box<dyn Read>, colors: ColorChannels) -> Result<Box<dyn DynDecoder>> {
+        Ok(Box::new(Stream(D::new(input, colors)?)))
+    }
+}
+
+/// The factory for a `SeekDecoder`, which reads its input as it goes.  It
+/// needs seekable input (see `DecoderFactory::create_seekable`).
+pub struct SeekFactory<D>(PhantomData<fn() -> D>);
+
+impl<D> SeekFactory<D> {
+    /// Create the factory.
+    pub fn new() -> SeekFactory<D> {
+        SeekFactory(PhantomData)
+    }
+}
+
+impl<D> Default for SeekFactory<D> {
+    fn default() -> Self {
+        SeekFactory::new()
+    }
+}
+
+/// A `SeekDecoder` as a `DynDecoder`.
+struct Seekable<D>(D);
+
+impl<D: SeekDecoder<Box<dyn ReadSeek>>> DynDecoder for Seekable<D> {
+    fn info(&self) -> StreamInfo {
+        self.0.info()
+    }
+
+    fn run(&mut self, audio: &mut Option<Audio>, video: &mut Option<Video>) -> Result<bool> {
+        self.0.run(audio, video)
+    }
+
+    fn select(&mut self, tracks: &[u32]) -> Result<()> {
+        self.0.select(tracks)
+    }
+
+    fn run_tracks(&mut self, tracks: &mut Tracks) -> Result<bool> {
+        self.0.run_tracks(tracks)
+    }
+
+    fn get(&self) -> Index {
+        self.0.get()
+    }
+
+    fn set(&mut self, index: Index) -> Result<()> {
+        self.0.set(index)
+    }
+
+    fn keyframes(&self) -> Option<Vec<Index>> {
+        self.0.keyframes()
+    }
+
+    fn seek(&mut self, index: Index, mode: SeekMode) -> Result<Index> {
+        self.0.seek(index, mode)
+    }
+}
+
+impl<D: SeekDecoder<Box<dyn ReadSeek>> + 'static> DecoderFactory for SeekFactory<D> {
+    fn capabilities(&self) -> Capabilities {
+        D::CAPABILITIES
+    }
+
+    /// Fails with `Error::UnsupportedFeature`, because the input can't seek.
+    fn create(&self, _input: Box<dyn Read>, _colors: ColorChannels) -> Result<Box<dyn DynDecoder>> {
+        Err(Error::UnsupportedFeature("non-seekable input".to_string()))
+    }
+
+    fn create_seekable(
+        &self,
+        input: Box<dyn ReadSeek>,
+        colors: ColorChannels,
+    ) -> Result<Box<dyn DynDecoder>> {
+        Ok(Box::new(Seekable(D::new(input, colors)?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use Format;
+    use Hint;
+    use Input;
+    use Registry;
+    use Signature;
+    use Timebase;
+
+    /// A "decoder" that makes one video frame per input byte.
+    struct Bytes {
+        data: Vec<u8>,
+        frame: u32,
+    }
+
+    impl Decoder<Vec<u8>> for Bytes {
+        const CAPABILITIES: Capabilities = Capabilities {
+            seeking: true,
+            ..Capabilities::NONE
+        };
+
+        fn new(data: Vec<u8>, _colors: ColorChannels) -> Result<Self> {
+            Ok(Bytes { data, frame: 0 })
+        }
+
+        fn run(&mut self, _: &mut Option<Audio>, _: &mut Option<Video>) -> Result<bool> {
+            self.frame += 1;
+            Ok(self.frame as usize <= self.data.len())
+        }
+
+        fn get(&self) -> Index {
+            Index::new(self.frame, Timebase::FPS_24)
+        }
+
+        fn set(&mut self, index: Index) {
+            self.frame = index.frame;
+        }
+    }
+
+    #[test]
+    fn boxed_decoder() {
+        let factories: Vec<Box<dyn DecoderFactory>> = vec![Box::new(Factory::<Bytes>::new())];
+        let input = Box::new(Cursor::new(vec![1, 2]));
+        let mut decoder = factories[0].create(input, ColorChannels::Srgba).unwrap();
+        let (mut audio, mut video) = (None, None);
+        assert!(decoder.run(&mut audio, &mut video).unwrap());
+        assert!(decoder.run(&mut audio, &mut video).unwrap());
+        assert!(!decoder.run(&mut audio, &mut video).unwrap());
+        decoder.set(Index::new(0, Timebase::FPS_24)).unwrap();
+        assert_eq!(decoder.get().frame, 0);
+    }
+
+    /// A streaming "decoder" that makes one video frame per input byte.
+    struct Trickle {
+        input: Box<dyn Read>,
+        frame: u32,
+    }
+
+    impl StreamDecoder<Box<dyn Read>> for Trickle {
+        const CAPABILITIES: Capabilities = Capabilities::NONE;
+
+        fn new(input: Box<dyn Read>, _colors: ColorChannels) -> Result<Self> {
+            Ok(Trickle { input, frame: 0 })
+        }
+
+        fn run(&mut self, _: &mut Option<Audio>, _: &mut Option<Video>) -> Result<bool> {
+            let mut byte = [0];
+            let more = self.input.read(&mut byte)? == 1;
+            self.frame += more as u32;
+            Ok(more)
+        }
+
+        fn get(&self) -> Index {
+            Index::new(self.frame, Timebase::FPS_24)
+        }
+    }
+
+    #[test]
+    fn probed_stream() {
+        const TRICKLE: Format = Format {
+            name: "Trickle",
+            signatures: &[Signature(&[(0, b"TR")])],
+            extensions: &[],
+            mime_types: &[],
+        };
+        let mut registry: Registry<Box<dyn DecoderFactory>> = Registry::new();
+        registry.register(TRICKLE, Box::new(StreamFactory::<Trickle>::new()));
+
+        // A pipe: it can only be read once, and not seeked.
+        let pipe: &'static [u8] = b"TRICKLE";
+        let mut input = Input::new(pipe);
+        let found = registry.probe_input(&mut input, &Hint::default()).unwrap();
+        let factory = found[0].codec;
+        let mut decoder = factory
+            .create(Box::new(input), ColorChannels::Srgba)
+            .unwrap();
+        let (mut audio, mut video) = (None, None);
+        while decoder.run(&mut audio, &mut video).unwrap() {}
+        assert_eq!(decoder.get().frame, 7);
+        assert!(decoder.set(Index::new(0, Timebase::FPS_24)).is_err());
+    }
+}
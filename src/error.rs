@@ -0,0 +1,60 @@
+// Copyright Jeron Lau 2017 - 2018.
+// Dual-licensed under either the MIT License or the Boost Software License, Version 1.0.
+// (See accompanying file LICENSE_1_0.txt or copy at https://www.boost.org/LICENSE_1_0.txt)
+
+use std::{error, fmt, io, result};
+
+/// An error from a decoder, encoder or tag reader.
+#[derive(Debug)]
+pub enum Error {
+    /// The data isn't in a format this decoder can handle: try a different
+    /// decoder.
+    UnsupportedFormat,
+    /// The data is in the right format, but is corrupt.
+    Corrupt(String),
+    /// The data ended before it was supposed to.
+    UnexpectedEof,
+    /// The format is right, but it uses a feature that isn't supported (yet).
+    UnsupportedFeature(String),
+    /// An `EncoderOptions` option isn't supported, or its value is invalid.
+    InvalidOption(String),
+    /// Reading or writing failed.
+    Io(io::Error),
+}
+
+/// A `Result` with this crate's `Error`.
+pub type Result<T> = result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::UnsupportedFormat => write!(f, "unsupported format"),
+            Error::Corrupt(ref why) => write!(f, "corrupt data: {}", why),
+            Error::UnexpectedEof => write!(f, "unexpected end of data"),
+            Error::UnsupportedFeature(ref what) => {
+                write!(f, "unsupported feature: {}", what)
+            }
+            Error::InvalidOption(ref why) => write!(f, "invalid option: {}", why),
+            Error::Io(ref e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        if e.kind() == io::ErrorKind::UnexpectedEof {
+            Error::UnexpectedEof
+        } else {
+            Error::Io(e)
+        }
+    }
+}
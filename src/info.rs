@@ -0,0 +1,125 @@
+// Copyright Jeron Lau 2017 - 2018.
+// Dual-licensed under either the MIT License or the Boost Software License, Version 1.0.
+// (See accompanying file LICENSE_1_0.txt or copy at https://www.boost.org/LICENSE_1_0.txt)
+
+use Audio;
+use AudioChannels;
+use ColorChannels;
+use Depth;
+use Metadata;
+use SampleFormat;
+use Timebase;
+use Track;
+use Video;
+
+/// What's known about a video stream before decoding it.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct VideoInfo {
+    /// The width and height.
+    pub wh: (u16, u16),
+    /// The color format that frames will be decoded to.
+    pub format: ColorChannels,
+    /// How the color components will be stored.
+    pub depth: Depth,
+    /// The frame rate.
+    pub timebase: Timebase,
+    /// The number of frames, if known.
+    pub n_frames: Option<u32>,
+}
+
+impl VideoInfo {
+    /// Get the duration in seconds, if known.
+    pub fn duration(&self) -> Option<f64> {
+        self.n_frames.map(|n| self.timebase.to_seconds(n as i64))
+    }
+
+    /// Create an empty `Video` to decode this stream into.
+    pub fn video(&self) -> Video {
+        let mut video = Video::new(self.format, self.wh, self.n_frames.unwrap_or(0));
+        video.set_timebase(self.timebase);
+        video.set_depth(self.depth);
+        video
+    }
+}
+
+/// What's known about an audio stream before decoding it.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct AudioInfo {
+    /// Samples per second, for each channel.
+    pub sample_rate: u32,
+    /// The channel layout.
+    pub channels: AudioChannels,
+    /// The format of the samples as stored in the file.
+    pub format: SampleFormat,
+    /// The number of samples for each channel, if known.
+    pub n_samples: Option<u64>,
+}
+
+impl AudioInfo {
+    /// Get the duration in seconds, if known.
+    pub fn duration(&self) -> Option<f64> {
+        self.n_samples.map(|n| n as f64 / self.sample_rate as f64)
+    }
+
+    /// Create an empty `Audio` to decode this stream into.
+    pub fn audio(&self) -> Audio {
+        Audio::new(self.channels, self.sample_rate, 0)
+    }
+}
+
+/// What's in a file, known right after creating the decoder (see
+/// `Decoder::info`).
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct StreamInfo {
+    /// The (default) video stream, if there is one.
+    pub video: Option<VideoInfo>,
+    /// The (default) audio stream, if there is one.
+    pub audio: Option<AudioInfo>,
+    /// Every track, for files with more than one video, audio or subtitle
+    /// stream (empty if the decoder doesn't support tracks).
+    pub tracks: Vec<Track>,
+    /// Title, artist, cover art, etc.
+    pub metadata: Metadata,
+}
+
+impl StreamInfo {
+    /// Get the duration of the longest stream in seconds, if known.
+    pub fn duration(&self) -> Option<f64> {
+        let video = self.video.and_then(|v| v.duration());
+        let audio = self.audio.and_then(|a| a.duration());
+        match (video, audio) {
+            (Some(v), Some(a)) => Some(v.max(a)),
+            (v, a) => v.or(a),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duration() {
+        let mut info = StreamInfo::default();
+        assert_eq!(info.duration(), None);
+        info.video = Some(VideoInfo {
+            wh: (1920, 1080),
+            format: ColorChannels::Srgb,
+            depth: Depth::U16,
+            timebase: Timebase::FPS_25,
+            n_frames: Some(250),
+        });
+        info.audio = Some(AudioInfo {
+            sample_rate: 48_000,
+            channels: AudioChannels::Stereo,
+            format: SampleFormat::I16,
+            n_samples: Some(528_000),
+        });
+        assert_eq!(info.duration(), Some(11.0));
+        let video = info.video.unwrap().video();
+        assert_eq!(
+            (video.wh(), video.timebase(), video.depth()),
+            ((1920, 1080), Timebase::FPS_25, Depth::U16)
+        );
+    }
+}
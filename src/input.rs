@@ -0,0 +1,248 @@
+// Copyright Jeron Lau 2017 - 2018.
+// Dual-licensed under either the MIT License or the Boost Software License, Version 1.0.
+// (See accompanying file LICENSE_1_0.txt or copy at https://www.boost.org/LICENSE_1_0.txt)
+
+use std::io::{self, BufRead, Read, Seek, SeekFrom};
+
+use Error;
+use Result;
+
+/// The default buffer size for `Input`.
+const CAPACITY: usize = 64 * 1024;
+
+/// Buffered input for `StreamDecoder`s: read, peek at and skip bytes of a
+/// stream while only keeping a small buffer in memory.
+pub struct Input<R> {
+    inner: R,
+    buf: Vec<u8>,
+    start: usize,
+    end: usize,
+    pos: u64,
+}
+
+impl<R: Read> Input<R> {
+    /// Create buffered input with the default (64 KiB) buffer.
+    pub fn new(inner: R) -> Input<R> {
+        Input::with_capacity(CAPACITY, inner)
+    }
+
+    /// Create buffered input with a buffer of `capacity` bytes.  The buffer
+    /// only grows if `peek` asks for more.
+    pub fn with_capacity(capacity: usize, inner: R) -> Input<R> {
+        Input {
+            inner,
+            buf: vec![0; capacity.max(1)],
+            start: 0,
+            end: 0,
+            pos: 0,
+        }
+    }
+
+    /// Get the position in the stream: the number of bytes read or skipped
+    /// (counting from 0, or from wherever `seek` last moved to).
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Look at the next `len` bytes without consuming them.  Fails with
+    /// `Error::UnexpectedEof` if the stream ends first.
+    pub fn peek(&mut self, len: usize) -> Result<&[u8]> {
+        if self.peek_up_to(len)?.len() < len {
+            return Err(Error::UnexpectedEof);
+        }
+        Ok(&self.buf[self.start..self.start + len])
+    }
+
+    /// Look at up to `len` of the next bytes without consuming them, fewer
+    /// if the stream ends first.
+    pub fn peek_up_to(&mut self, len: usize) -> Result<&[u8]> {
+        if self.end - self.start < len {
+            // Move what's left to the front, and make room for the rest.
+            self.buf.copy_within(self.start..self.end, 0);
+            self.end -= self.start;
+            self.start = 0;
+            if self.buf.len() < len {
+                self.buf.resize(len, 0);
+            }
+            while self.end < len {
+                match self.inner.read(&mut self.buf[self.end..]) {
+                    Ok(0) => break,
+                    Ok(n) => self.end += n,
+                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        }
+        Ok(&self.buf[self.start..self.end.min(self.start + len)])
+    }
+
+    /// Read the next `len` bytes.  Fails with `Error::UnexpectedEof` if the
+    /// stream ends first.
+    pub fn read_bytes(&mut self, len: usize) -> Result<&[u8]> {
+        self.peek(len)?;
+        let start = self.start;
+        BufRead::consume(self, len);
+        Ok(&self.buf[start..start + len])
+    }
+
+    /// Read a byte.
+    pub fn u8(&mut self) -> Result<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    /// Read a big-endian u16.
+    pub fn u16_be(&mut self) -> Result<u16> {
+        let b = self.read_bytes(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    /// Read a little-endian u16.
+    pub fn u16_le(&mut self) -> Result<u16> {
+        let b = self.read_bytes(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    /// Read a big-endian u32.
+    pub fn u32_be(&mut self) -> Result<u32> {
+        let b = self.read_bytes(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// Read a little-endian u32.
+    pub fn u32_le(&mut self) -> Result<u32> {
+        let b = self.read_bytes(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// Skip the next `len` bytes.  Fails with `Error::UnexpectedEof` if the
+    /// stream ends first.
+    pub fn skip(&mut self, mut len: u64) -> Result<()> {
+        while len > 0 {
+            let n = {
+                let buf = self.fill_buf()?;
+                if buf.is_empty() {
+                    return Err(Error::UnexpectedEof);
+                }
+                (buf.len() as u64).min(len) as usize
+            };
+            BufRead::consume(self, n);
+            len -= n as u64;
+        }
+        Ok(())
+    }
+
+    /// Return true if there's nothing left to read.
+    pub fn is_eof(&mut self) -> Result<bool> {
+        Ok(self.fill_buf()?.is_empty())
+    }
+
+    /// Get a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Get the underlying reader, dropping any buffered bytes.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read + Seek> Input<R> {
+    /// Seek to an absolute byte position in the underlying reader.  If it's
+    /// in the buffer, no reading is needed.
+    pub fn seek(&mut self, pos: u64) -> Result<()> {
+        let buffered = self.pos - self.start as u64..self.pos + (self.end - self.start) as u64;
+        if buffered.contains(&pos) {
+            self.start = (pos - buffered.start) as usize;
+        } else {
+            self.inner.seek(SeekFrom::Start(pos))?;
+            self.start = 0;
+            self.end = 0;
+        }
+        self.pos = pos;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for Input<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let n = {
+            let buf = self.fill_buf()?;
+            let n = buf.len().min(out.len());
+            out[..n].copy_from_slice(&buf[..n]);
+            n
+        };
+        BufRead::consume(self, n);
+        Ok(n)
+    }
+}
+
+impl<R: Read> BufRead for Input<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.start == self.end {
+            self.start = 0;
+            self.end = loop {
+                match self.inner.read(&mut self.buf) {
+                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    result => break result?,
+                }
+            };
+        }
+        Ok(&self.buf[self.start..self.end])
+    }
+
+    fn consume(&mut self, len: usize) {
+        let len = len.min(self.end - self.start);
+        self.start += len;
+        self.pos += len as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// A reader that only gives 3 bytes at a time, like a pipe.
+    struct Trickle(Cursor<Vec<u8>>);
+
+    impl Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = buf.len().min(3);
+            self.0.read(&mut buf[..len])
+        }
+    }
+
+    #[test]
+    fn small_buffer() {
+        let data: Vec<u8> = (0..100).collect();
+        let mut input = Input::with_capacity(4, Trickle(Cursor::new(data)));
+        assert_eq!(input.peek(2).unwrap(), &[0, 1]);
+        assert_eq!(input.peek_up_to(6).unwrap(), &[0, 1, 2, 3, 4, 5]);
+        assert_eq!(
+            input.read_bytes(10).unwrap(),
+            &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9]
+        );
+        assert_eq!(input.u16_be().unwrap(), 0x0A0B);
+        input.skip(80).unwrap();
+        assert_eq!(input.position(), 92);
+        assert_eq!(input.u32_le().unwrap(), 0x5F5E5D5C);
+        assert!(matches!(input.read_bytes(5), Err(Error::UnexpectedEof)));
+        assert_eq!(input.peek_up_to(5).unwrap(), &[96, 97, 98, 99]);
+    }
+
+    #[test]
+    fn seek() {
+        let data: Vec<u8> = (0..100).collect();
+        let mut input = Input::with_capacity(8, Cursor::new(data));
+        input.skip(4).unwrap();
+        input.seek(2).unwrap();
+        assert_eq!(input.u8().unwrap(), 2);
+        input.seek(50).unwrap();
+        assert_eq!(input.u8().unwrap(), 50);
+        assert_eq!(input.position(), 51);
+        assert!(!input.is_eof().unwrap());
+        input.skip(49).unwrap();
+        assert!(input.is_eof().unwrap());
+    }
+}
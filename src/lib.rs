@@ -18,11 +18,35 @@
 )]
 
 mod audio;
+mod capabilities;
 mod codec;
+mod dyn_codec;
+mod error;
+mod info;
+mod input;
+mod metadata;
+mod options;
 mod path_op;
+mod registry;
+mod subtitle;
+mod tag;
+mod time;
+mod track;
 mod video;
 
 pub use audio::*;
+pub use capabilities::*;
 pub use codec::*;
+pub use dyn_codec::*;
+pub use error::*;
+pub use info::*;
+pub use input::*;
+pub use metadata::*;
+pub use options::*;
 pub use path_op::*;
+pub use registry::*;
+pub use subtitle::*;
+pub use tag::*;
+pub use time::*;
+pub use track::*;
 pub use video::*;
@@ -0,0 +1,462 @@
+// Copyright Jeron Lau 2017 - 2018.
+// Dual-licensed under either the MIT License or the Boost Software License, Version 1.0.
+// (See accompanying file LICENSE_1_0.txt or copy at https://www.boost.org/LICENSE_1_0.txt)
+
+use std::fmt;
+use std::slice;
+
+/// A calendar date, as precise as is known.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Date {
+    /// Year
+    pub year: i32,
+    /// Month (1-12)
+    pub month: Option<u8>,
+    /// Day of the month (1-31), only known if the month is.
+    pub day: Option<u8>,
+}
+
+impl Date {
+    /// Create a date of just a year.
+    pub fn year(year: i32) -> Date {
+        Date {
+            year,
+            month: None,
+            day: None,
+        }
+    }
+
+    /// Create a full date.
+    pub fn new(year: i32, month: u8, day: u8) -> Date {
+        Date {
+            year,
+            month: Some(month),
+            day: Some(day),
+        }
+    }
+
+    /// Parse a date from `YYYY`, `YYYY-MM` or `YYYY-MM-DD` (anything after
+    /// the day, like a time, is ignored).
+    pub fn parse(text: &str) -> Option<Date> {
+        let text = text.trim();
+        let mut parts = text.splitn(3, '-');
+        let year = parts.next()?;
+        if year.len() != 4 {
+            return None;
+        }
+        let year = year.parse().ok()?;
+        let month = match parts.next() {
+            Some(m) => Some(m.get(..2)?.parse().ok().filter(|m| (1..=12).contains(m))?),
+            None => None,
+        };
+        let day = match parts.next() {
+            Some(d) => Some(d.get(..2)?.parse().ok().filter(|d| (1..=31).contains(d))?),
+            None => None,
+        };
+        Some(Date { year, month, day })
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}", self.year)?;
+        if let Some(month) = self.month {
+            write!(f, "-{:02}", month)?;
+            if let Some(day) = self.day {
+                write!(f, "-{:02}", day)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What an embedded picture is of (the ID3v2 / FLAC picture types).
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum PictureKind {
+    /// Other
+    Other = 0,
+    /// 32x32 pixels file icon (PNG only).
+    FileIcon = 1,
+    /// Other file icon.
+    OtherFileIcon = 2,
+    /// Cover (front).
+    FrontCover = 3,
+    /// Cover (back).
+    BackCover = 4,
+    /// Leaflet page.
+    Leaflet = 5,
+    /// Media (e.g. label side of CD).
+    Media = 6,
+    /// Lead artist / lead performer / soloist.
+    LeadArtist = 7,
+    /// Artist / performer.
+    Artist = 8,
+    /// Conductor.
+    Conductor = 9,
+    /// Band / orchestra.
+    Band = 10,
+    /// Composer.
+    Composer = 11,
+    /// Lyricist / text writer.
+    Lyricist = 12,
+    /// Recording location.
+    RecordingLocation = 13,
+    /// During recording.
+    DuringRecording = 14,
+    /// During performance.
+    DuringPerformance = 15,
+    /// Movie / video screen capture.
+    ScreenCapture = 16,
+    /// A bright coloured fish.
+    BrightFish = 17,
+    /// Illustration.
+    Illustration = 18,
+    /// Band / artist logotype.
+    BandLogo = 19,
+    /// Publisher / studio logotype.
+    PublisherLogo = 20,
+}
+
+impl PictureKind {
+    /// Get the picture type from its ID3v2 / FLAC number.  Unknown numbers
+    /// are `Other`.
+    pub fn from_u8(n: u8) -> PictureKind {
+        use self::PictureKind::*;
+
+        const KINDS: [PictureKind; 21] = [
+            Other,
+            FileIcon,
+            OtherFileIcon,
+            FrontCover,
+            BackCover,
+            Leaflet,
+            Media,
+            LeadArtist,
+            Artist,
+            Conductor,
+            Band,
+            Composer,
+            Lyricist,
+            RecordingLocation,
+            DuringRecording,
+            DuringPerformance,
+            ScreenCapture,
+            BrightFish,
+            Illustration,
+            BandLogo,
+            PublisherLogo,
+        ];
+        KINDS.get(n as usize).cloned().unwrap_or(Other)
+    }
+}
+
+/// An embedded picture, like cover art.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Picture {
+    /// What the picture is of.
+    pub kind: PictureKind,
+    /// MIME type of `data` (`image/png`, `image/jpeg`, etc.)
+    pub mime: String,
+    /// Description of the picture.
+    pub description: String,
+    /// The encoded image file.
+    pub data: Vec<u8>,
+}
+
+/// A metadata value.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Value {
+    /// Text
+    Text(String),
+    /// A whole number, like a track number.
+    Integer(i64),
+    /// A date, like a release date.
+    Date(Date),
+    /// An embedded picture.
+    Picture(Picture),
+}
+
+impl Value {
+    /// Get the value as text, if it is text.
+    pub fn as_text(&self) -> Option<&str> {
+        match *self {
+            Value::Text(ref text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Get the value as an integer, if it is one (or is text of one).
+    pub fn as_integer(&self) -> Option<i64> {
+        match *self {
+            Value::Integer(n) => Some(n),
+            Value::Text(ref text) => text.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Get the value as a date, if it is one (or is text of one).
+    pub fn as_date(&self) -> Option<Date> {
+        match *self {
+            Value::Date(date) => Some(date),
+            Value::Text(ref text) => Date::parse(text),
+            _ => None,
+        }
+    }
+
+    /// Get the value as a picture, if it is one.
+    pub fn as_picture(&self) -> Option<&Picture> {
+        match *self {
+            Value::Picture(ref picture) => Some(picture),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Text(ref text) => write!(f, "{}", text),
+            Value::Integer(n) => write!(f, "{}", n),
+            Value::Date(date) => write!(f, "{}", date),
+            Value::Picture(ref p) => write!(f, "[{} {:?}]", p.mime, p.kind),
+        }
+    }
+}
+
+impl From<String> for Value {
+    fn from(text: String) -> Value {
+        Value::Text(text)
+    }
+}
+
+impl<'a> From<&'a str> for Value {
+    fn from(text: &'a str) -> Value {
+        Value::Text(text.to_string())
+    }
+}
+
+impl From<i64> for Value {
+    fn from(n: i64) -> Value {
+        Value::Integer(n)
+    }
+}
+
+impl From<Date> for Value {
+    fn from(date: Date) -> Value {
+        Value::Date(date)
+    }
+}
+
+impl From<Picture> for Value {
+    fn from(picture: Picture) -> Value {
+        Value::Picture(picture)
+    }
+}
+
+/// A metadata key.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Key {
+    /// Title (text)
+    Title,
+    /// Artist / Author (text)
+    Artist,
+    /// Album Artist (text)
+    AlbumArtist,
+    /// Album (text)
+    Album,
+    /// Disc number (integer)
+    Disc,
+    /// Disc count (integer)
+    DiscCount,
+    /// Release Date / Release Year (date)
+    Release,
+    /// Track # (integer)
+    TrackNumber,
+    /// Track Count (integer)
+    TrackCount,
+    /// Genre (text)
+    Genre,
+    /// Comment (text)
+    Comment,
+    /// Composer (text)
+    Composer,
+    /// Original artist (text)
+    OriginalArtist,
+    /// Copyright / License (text)
+    Copyright,
+    /// Artist Website / URL (text)
+    Url,
+    /// Encoded By (text)
+    EncodedBy,
+    /// Embedded picture / cover art (picture)
+    Picture,
+    /// Any other key.  Names are compared as given, so use upper case.
+    Custom(String),
+}
+
+/// Well-known keys and their names.
+const KEYS: [(Key, &str); 17] = [
+    (Key::Title, "TITLE"),
+    (Key::Artist, "ARTIST"),
+    (Key::AlbumArtist, "ALBUMARTIST"),
+    (Key::Album, "ALBUM"),
+    (Key::Disc, "DISCNUMBER"),
+    (Key::DiscCount, "DISCTOTAL"),
+    (Key::Release, "DATE"),
+    (Key::TrackNumber, "TRACKNUMBER"),
+    (Key::TrackCount, "TRACKTOTAL"),
+    (Key::Genre, "GENRE"),
+    (Key::Comment, "COMMENT"),
+    (Key::Composer, "COMPOSER"),
+    (Key::OriginalArtist, "ORIGINALARTIST"),
+    (Key::Copyright, "COPYRIGHT"),
+    (Key::Url, "URL"),
+    (Key::EncodedBy, "ENCODEDBY"),
+    (Key::Picture, "PICTURE"),
+];
+
+impl Key {
+    /// Get a key from its name (case-insensitive), which is `Custom` if it
+    /// isn't a well-known key.
+    pub fn new(name: &str) -> Key {
+        let upper = name.to_uppercase();
+        for (key, n) in KEYS.iter() {
+            if *n == upper {
+                return key.clone();
+            }
+        }
+        Key::Custom(upper)
+    }
+
+    /// Get the name of the key (upper case, Vorbis comment style).
+    pub fn name(&self) -> &str {
+        if let Key::Custom(ref name) = *self {
+            return name;
+        }
+        KEYS.iter().find(|&(k, _)| k == self).unwrap().1
+    }
+}
+
+/// Metadata for `Audio` and `Video`: an ordered list of key / value tags,
+/// where each key can have multiple values.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Metadata {
+    tags: Vec<(Key, Value)>,
+}
+
+impl Metadata {
+    /// Create empty metadata.
+    pub fn new() -> Metadata {
+        Metadata { tags: Vec::new() }
+    }
+
+    /// Return the number of tags.
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    /// Return true if there are no tags.
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    /// Add a value to a key, after any existing values.
+    pub fn add<V: Into<Value>>(&mut self, key: Key, value: V) {
+        self.tags.push((key, value.into()));
+    }
+
+    /// Set the only value of a key, replacing any existing values.
+    pub fn set<V: Into<Value>>(&mut self, key: Key, value: V) {
+        self.remove(&key);
+        self.add(key, value);
+    }
+
+    /// Remove all values of a key, returning how many there were.
+    pub fn remove(&mut self, key: &Key) -> usize {
+        let len = self.tags.len();
+        self.tags.retain(|(k, _)| k != key);
+        len - self.tags.len()
+    }
+
+    /// Get the first value of a key.
+    pub fn get(&self, key: &Key) -> Option<&Value> {
+        self.tags.iter().find(|&(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    /// Iterate over all the values of a key.
+    pub fn get_all<'a>(&'a self, key: &'a Key) -> impl Iterator<Item = &'a Value> + 'a {
+        self.tags
+            .iter()
+            .filter(move |&(k, _)| k == key)
+            .map(|(_, v)| v)
+    }
+
+    /// Get the first value of a key as text.
+    pub fn text(&self, key: &Key) -> Option<&str> {
+        self.get(key)?.as_text()
+    }
+
+    /// Get the first value of a key as an integer.
+    pub fn integer(&self, key: &Key) -> Option<i64> {
+        self.get(key)?.as_integer()
+    }
+
+    /// Get the first value of a key as a date.
+    pub fn date(&self, key: &Key) -> Option<Date> {
+        self.get(key)?.as_date()
+    }
+
+    /// Iterate over the embedded pictures.
+    pub fn pictures(&self) -> impl Iterator<Item = &Picture> {
+        self.get_all(&Key::Picture).filter_map(Value::as_picture)
+    }
+
+    /// Iterate over all of the tags, in order.
+    pub fn iter(&self) -> slice::Iter<'_, (Key, Value)> {
+        self.tags.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Metadata {
+    type Item = &'a (Key, Value);
+    type IntoIter = slice::Iter<'a, (Key, Value)>;
+
+    fn into_iter(self) -> slice::Iter<'a, (Key, Value)> {
+        self.tags.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates() {
+        assert_eq!(Date::parse("1999"), Some(Date::year(1999)));
+        assert_eq!(Date::parse("2018-03-09T10:00"), Some(Date::new(2018, 3, 9)));
+        assert_eq!(Date::parse("2018-13"), None);
+        assert_eq!(Date::parse("18"), None);
+        assert_eq!(Date::new(2018, 3, 9).to_string(), "2018-03-09");
+    }
+
+    #[test]
+    fn multiple_values() {
+        let mut metadata = Metadata::new();
+        metadata.add(Key::Artist, "A");
+        metadata.add(Key::new("artist"), "B");
+        metadata.add(Key::TrackNumber, "7");
+        metadata.add(Key::new("mood"), "calm");
+        let artists: Vec<_> = metadata.get_all(&Key::Artist).collect();
+        assert_eq!(artists.len(), 2);
+        assert_eq!(metadata.integer(&Key::TrackNumber), Some(7));
+        assert_eq!(
+            metadata.text(&Key::Custom("MOOD".to_string())),
+            Some("calm")
+        );
+
+        metadata.set(Key::Artist, "C");
+        assert_eq!(metadata.text(&Key::Artist), Some("C"));
+        assert_eq!(metadata.len(), 3);
+    }
+}
@@ -0,0 +1,138 @@
+// Copyright Jeron Lau 2017 - 2018.
+// Dual-licensed under either the MIT License or the Boost Software License, Version 1.0.
+// (See accompanying file LICENSE_1_0.txt or copy at https://www.boost.org/LICENSE_1_0.txt)
+
+use Error;
+use Result;
+
+/// An option that an encoder can support (see `EncoderV::OPTIONS`).
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum EncoderOption {
+    /// `EncoderOptions::quality`
+    Quality,
+    /// `EncoderOptions::bitrate`
+    Bitrate,
+    /// `EncoderOptions::compression`
+    Compression,
+    /// `EncoderOptions::lossless`
+    Lossless,
+    /// `EncoderOptions::interlace`
+    Interlace,
+    /// An encoder-specific option in `EncoderOptions::extra`, by name.
+    Extra(&'static str),
+}
+
+/// Options for creating an encoder.  Options that are `None` are left up to
+/// the encoder.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct EncoderOptions {
+    /// Quality for lossy formats, from 0.0 (smallest) to 1.0 (best).
+    pub quality: Option<f32>,
+    /// Target bitrate, in bits per second.
+    pub bitrate: Option<u32>,
+    /// Compression level for lossless formats (higher is smaller, but
+    /// slower).  The range depends on the format, like 0-9 for PNG.
+    pub compression: Option<u8>,
+    /// Whether to encode without losing any information.
+    pub lossless: Option<bool>,
+    /// Whether to interlace the output (like Adam7 for PNG).
+    pub interlace: Option<bool>,
+    /// Encoder-specific options, as name / value pairs.
+    pub extra: Vec<(String, String)>,
+}
+
+impl EncoderOptions {
+    /// Create options that leave everything up to the encoder.
+    pub fn new() -> EncoderOptions {
+        EncoderOptions::default()
+    }
+
+    /// Set an encoder-specific option, replacing its old value.
+    pub fn set(&mut self, name: &str, value: &str) {
+        match self.extra.iter_mut().find(|(n, _)| n == name) {
+            Some(option) => option.1 = value.to_string(),
+            None => self.extra.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    /// Get an encoder-specific option.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.extra
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Get the typed options that are set (not including `extra`).
+    pub fn used(&self) -> Vec<EncoderOption> {
+        let mut used = Vec::new();
+        if self.quality.is_some() {
+            used.push(EncoderOption::Quality);
+        }
+        if self.bitrate.is_some() {
+            used.push(EncoderOption::Bitrate);
+        }
+        if self.compression.is_some() {
+            used.push(EncoderOption::Compression);
+        }
+        if self.lossless.is_some() {
+            used.push(EncoderOption::Lossless);
+        }
+        if self.interlace.is_some() {
+            used.push(EncoderOption::Interlace);
+        }
+        used
+    }
+
+    /// Check that the options are valid, and only use `supported` options.
+    /// Fails with `Error::InvalidOption` otherwise.
+    pub fn check(&self, supported: &[EncoderOption]) -> Result<()> {
+        for option in self.used() {
+            if !supported.contains(&option) {
+                return Err(Error::InvalidOption(format!(
+                    "{:?} isn't supported",
+                    option
+                )));
+            }
+        }
+        for (name, _) in &self.extra {
+            let extra = |o: &EncoderOption| match *o {
+                EncoderOption::Extra(n) => n == name,
+                _ => false,
+            };
+            if !supported.iter().any(extra) {
+                return Err(Error::InvalidOption(format!("{} isn't supported", name)));
+            }
+        }
+        match self.quality {
+            Some(q) if !(0.0..=1.0).contains(&q) => Err(Error::InvalidOption(format!(
+                "quality {} isn't from 0.0 to 1.0",
+                q
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check() {
+        let supported = [EncoderOption::Quality, EncoderOption::Extra("speed")];
+        let mut options = EncoderOptions::new();
+        options.quality = Some(0.8);
+        options.set("speed", "4");
+        assert!(options.check(&supported).is_ok());
+        assert_eq!(options.get("speed"), Some("4"));
+        options.quality = Some(1.5);
+        assert!(options.check(&supported).is_err());
+        options.quality = None;
+        options.lossless = Some(true);
+        assert!(options.check(&supported).is_err());
+        options.lossless = None;
+        options.set("tune", "film");
+        assert!(options.check(&supported).is_err());
+    }
+}
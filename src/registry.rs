@@ -0,0 +1,299 @@
+// Copyright Jeron Lau 2017 - 2018.
+// Dual-licensed under either the MIT License or the Boost Software License, Version 1.0.
+// (See accompanying file LICENSE_1_0.txt or copy at https://www.boost.org/LICENSE_1_0.txt)
+
+use std::cmp::Reverse;
+use std::io::Read;
+use std::path::Path;
+
+use Input;
+use Result;
+
+/// Magic bytes that identify a format: each `(offset, bytes)` part must
+/// match.  For example, WAV is `Signature(&[(0, b"RIFF"), (8, b"WAVE")])`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Signature(pub &'static [(usize, &'static [u8])]);
+
+impl Signature {
+    /// Get the number of bytes needed at the start of the data to check
+    /// this signature.
+    pub fn len(&self) -> usize {
+        self.0.iter().map(|(o, b)| o + b.len()).max().unwrap_or(0)
+    }
+
+    /// Return true if the signature has no bytes (and matches anything).
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|(_, b)| b.is_empty())
+    }
+
+    /// Get the number of bytes matched, or `None` if `data` doesn't match.
+    pub fn matches(&self, data: &[u8]) -> Option<usize> {
+        let mut matched = 0;
+        for (offset, bytes) in self.0 {
+            if data.get(*offset..offset + bytes.len())? != *bytes {
+                return None;
+            }
+            matched += bytes.len();
+        }
+        Some(matched)
+    }
+}
+
+/// A description of a file format, for finding the codec for some data.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Format {
+    /// The name of the format, like "PNG".
+    pub name: &'static str,
+    /// Magic bytes, any of which identify the format.
+    pub signatures: &'static [Signature],
+    /// File extensions (without the `.`), like "png".
+    pub extensions: &'static [&'static str],
+    /// MIME types, like "image/png".
+    pub mime_types: &'static [&'static str],
+}
+
+/// What's known about the input besides its bytes, to help `probe`.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Hint {
+    /// The file extension (without the `.`).
+    pub extension: Option<String>,
+    /// The MIME type (from a web server, for instance).
+    pub mime: Option<String>,
+}
+
+impl Hint {
+    /// Create a hint from a file's path.
+    pub fn path<P: AsRef<Path>>(path: P) -> Hint {
+        Hint {
+            extension: path
+                .as_ref()
+                .extension()
+                .map(|e| e.to_string_lossy().into_owned()),
+            mime: None,
+        }
+    }
+
+    /// Create a hint from a MIME type.
+    pub fn mime(mime: &str) -> Hint {
+        Hint {
+            extension: None,
+            mime: Some(mime.to_string()),
+        }
+    }
+}
+
+/// A format that `Registry::probe` found, and how sure it is.
+#[derive(Debug)]
+pub struct Probe<'a, T: 'a> {
+    /// The format.
+    pub format: &'a Format,
+    /// What was registered for the format.
+    pub codec: &'a T,
+    /// Confidence from 10 (a guess from the extension) to 100 (a long
+    /// signature matched, and the hint agrees).
+    pub score: u8,
+}
+
+/// A list of formats and their codecs, which codec crates register with and
+/// applications use to pick a codec for some data.  `T` is whatever the
+/// application needs to create the codec, like a `Box<dyn DecoderFactory>`.
+#[derive(Debug)]
+pub struct Registry<T> {
+    codecs: Vec<(Format, T)>,
+}
+
+impl<T> Default for Registry<T> {
+    fn default() -> Self {
+        Registry::new()
+    }
+}
+
+impl<T> Registry<T> {
+    /// Create an empty registry.
+    pub fn new() -> Registry<T> {
+        Registry { codecs: Vec::new() }
+    }
+
+    /// Register a codec for a format.  Codecs registered first win ties.
+    pub fn register(&mut self, format: Format, codec: T) {
+        self.codecs.push((format, codec));
+    }
+
+    /// Get the number of registered codecs.
+    pub fn len(&self) -> usize {
+        self.codecs.len()
+    }
+
+    /// Return true if no codecs are registered.
+    pub fn is_empty(&self) -> bool {
+        self.codecs.is_empty()
+    }
+
+    /// Iterate over the registered formats and codecs.
+    pub fn iter(&self) -> impl Iterator<Item = (&Format, &T)> {
+        self.codecs.iter().map(|(f, c)| (f, c))
+    }
+
+    /// Get the codec for a format by name (case-insensitive).
+    pub fn by_name(&self, name: &str) -> Option<&T> {
+        self.find(|f| f.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Get the codec for a file extension (case-insensitive, no `.`).
+    pub fn by_extension(&self, extension: &str) -> Option<&T> {
+        self.find(|f| has(f.extensions, extension))
+    }
+
+    /// Get the codec for a MIME type (case-insensitive).
+    pub fn by_mime(&self, mime: &str) -> Option<&T> {
+        self.find(|f| has(f.mime_types, mime))
+    }
+
+    fn find<F: Fn(&Format) -> bool>(&self, f: F) -> Option<&T> {
+        self.codecs
+            .iter()
+            .find(|(format, _)| f(format))
+            .map(|(_, c)| c)
+    }
+
+    /// Get the number of bytes from the start of the input that `probe`
+    /// needs to check every signature.
+    pub fn header_len(&self) -> usize {
+        self.codecs
+            .iter()
+            .flat_map(|(f, _)| f.signatures)
+            .map(Signature::len)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Find the formats that `data` (the start of the input, see
+    /// `header_len`) could be in, best first.  Signatures count the most;
+    /// the hint adds to the score, or is used alone if nothing matched.
+    pub fn probe(&self, data: &[u8], hint: &Hint) -> Vec<Probe<'_, T>> {
+        let mut found: Vec<Probe<'_, T>> = self
+            .codecs
+            .iter()
+            .filter_map(|(format, codec)| {
+                let signature = format
+                    .signatures
+                    .iter()
+                    .filter_map(|s| s.matches(data))
+                    .max()
+                    .map(|bytes| 50 + 5 * bytes.min(8) as u8);
+                let extension = match hint.extension {
+                    Some(ref e) if has(format.extensions, e) => 10,
+                    _ => 0,
+                };
+                let mime = match hint.mime {
+                    Some(ref m) if has(format.mime_types, m) => 20,
+                    _ => 0,
+                };
+                let score = match signature {
+                    Some(score) => score + extension + mime,
+                    // A signature that didn't match rules the format out.
+                    None if !format.signatures.is_empty() => return None,
+                    None => extension + mime,
+                };
+                if score == 0 {
+                    return None;
+                }
+                Some(Probe {
+                    format,
+                    codec,
+                    score: score.min(100),
+                })
+            })
+            .collect();
+        found.sort_by_key(|p| Reverse(p.score));
+        found
+    }
+
+    /// Find the formats that `input` could be in (see `probe`), by peeking
+    /// at its start.  Nothing is consumed, so `input` can then be given to
+    /// the codec (see `DecoderFactory::create`).
+    pub fn probe_input<R: Read>(
+        &self,
+        input: &mut Input<R>,
+        hint: &Hint,
+    ) -> Result<Vec<Probe<'_, T>>> {
+        Ok(self.probe(input.peek_up_to(self.header_len())?, hint))
+    }
+
+    /// Find the format that `data` is most likely in (see `probe`).
+    pub fn best(&self, data: &[u8], hint: &Hint) -> Option<Probe<'_, T>> {
+        self.probe(data, hint).into_iter().next()
+    }
+}
+
+/// Return true if `list` has `item` (case-insensitive).
+fn has(list: &[&str], item: &str) -> bool {
+    list.iter().any(|i| i.eq_ignore_ascii_case(item))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WAV: Format = Format {
+        name: "WAV",
+        signatures: &[Signature(&[(0, b"RIFF"), (8, b"WAVE")])],
+        extensions: &["wav", "wave"],
+        mime_types: &["audio/wav", "audio/x-wav"],
+    };
+    const AVI: Format = Format {
+        name: "AVI",
+        signatures: &[Signature(&[(0, b"RIFF"), (8, b"AVI ")])],
+        extensions: &["avi"],
+        mime_types: &["video/x-msvideo"],
+    };
+    const RAW: Format = Format {
+        name: "Raw",
+        signatures: &[],
+        extensions: &["raw", "pcm"],
+        mime_types: &[],
+    };
+
+    fn registry() -> Registry<u32> {
+        let mut registry = Registry::new();
+        registry.register(RAW, 0);
+        registry.register(WAV, 1);
+        registry.register(AVI, 2);
+        registry
+    }
+
+    #[test]
+    fn probe() {
+        let registry = registry();
+        assert_eq!(registry.header_len(), 12);
+        let data = b"RIFF\x24\x00\x00\x00WAVEfmt ";
+        let best = registry.best(data, &Hint::default()).unwrap();
+        assert_eq!((*best.codec, best.score), (1, 90));
+        // A wrong extension doesn't beat the signature.
+        let found = registry.probe(data, &Hint::path("song.avi"));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].format.name, "WAV");
+        // No signature, so only the extension helps.
+        let best = registry.best(b"\x00\x01", &Hint::path("a/b.PCM")).unwrap();
+        assert_eq!((*best.codec, best.score), (0, 10));
+        assert!(registry.best(b"\x00\x01", &Hint::default()).is_none());
+
+        // Probing a stream doesn't consume it, even if it's short.
+        let mut input = Input::new(&data[..10]);
+        let found = registry.probe_input(&mut input, &Hint::default()).unwrap();
+        assert!(found.is_empty());
+        let mut input = Input::new(&data[..]);
+        let found = registry.probe_input(&mut input, &Hint::default()).unwrap();
+        assert_eq!(found[0].format.name, "WAV");
+        assert_eq!(input.position(), 0);
+    }
+
+    #[test]
+    fn lookup() {
+        let registry = registry();
+        assert_eq!(registry.by_name("avi"), Some(&2));
+        assert_eq!(registry.by_extension("WAVE"), Some(&1));
+        assert_eq!(registry.by_mime("audio/x-wav"), Some(&1));
+        assert_eq!(registry.by_mime("image/png"), None);
+    }
+}
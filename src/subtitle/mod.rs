@@ -0,0 +1,409 @@
+// Copyright Jeron Lau 2017 - 2018.
+// Dual-licensed under either the MIT License or the Boost Software License, Version 1.0.
+// (See accompanying file LICENSE_1_0.txt or copy at https://www.boost.org/LICENSE_1_0.txt)
+
+mod srt;
+mod webvtt;
+
+pub use self::srt::Srt;
+pub use self::webvtt::WebVtt;
+
+use std::collections::{vec_deque, VecDeque};
+
+use Index;
+use Metadata;
+use Timebase;
+use Timestamp;
+
+/// How a `Span` of text looks.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct Style {
+    /// Bold text.
+    pub bold: bool,
+    /// Italic text.
+    pub italic: bool,
+    /// Underlined text.
+    pub underline: bool,
+    /// sRGB text color, or `None` for the player's default.
+    pub color: Option<[u8; 3]>,
+}
+
+/// A run of text with one `Style`.  Lines are split with `\n`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Span {
+    /// The text.
+    pub text: String,
+    /// How the text looks.
+    pub style: Style,
+}
+
+impl Span {
+    /// Create a span of text.
+    pub fn new(text: &str, style: Style) -> Span {
+        Span {
+            text: text.to_string(),
+            style,
+        }
+    }
+}
+
+/// How lines of a `Cue` are aligned.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum Align {
+    /// Left (for left-to-right text).
+    Start,
+    /// Centered.
+    #[default]
+    Center,
+    /// Right (for left-to-right text).
+    End,
+}
+
+/// Where a `Cue` is shown on the video.  `None` leaves it up to the player
+/// (centered at the bottom).
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct Position {
+    /// Horizontal position, from 0.0 (left) to 1.0 (right).
+    pub x: Option<f32>,
+    /// Vertical position, from 0.0 (top) to 1.0 (bottom).
+    pub y: Option<f32>,
+    /// How lines are aligned.
+    pub align: Align,
+}
+
+/// Text shown from one time until another.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Cue {
+    /// An identifier, if the format has one.
+    pub id: Option<String>,
+    /// When the text is first shown.
+    pub start: Timestamp,
+    /// When the text stops being shown.
+    pub end: Timestamp,
+    /// The styled text.
+    pub spans: Vec<Span>,
+    /// Where the text is shown.
+    pub position: Position,
+}
+
+impl Cue {
+    /// Create a cue of plain text.
+    pub fn new(start: Timestamp, end: Timestamp, text: &str) -> Cue {
+        Cue {
+            id: None,
+            start,
+            end,
+            spans: vec![Span::new(text, Style::default())],
+            position: Position::default(),
+        }
+    }
+
+    /// Get the text without styling.
+    pub fn text(&self) -> String {
+        self.spans.iter().map(|s| s.text.as_str()).collect()
+    }
+}
+
+/// A Subtitle Buffer: timed text, like captions, to go with `Video`.
+pub struct Subtitles {
+    /// Title, language, etc.
+    pub metadata: Metadata,
+    timebase: Timebase,
+    cues: VecDeque<Cue>,
+}
+
+impl Subtitles {
+    /// Create a new subtitle buffer, with cue times counted in `timebase`.
+    pub fn new(timebase: Timebase) -> Subtitles {
+        Subtitles {
+            metadata: Metadata::new(),
+            timebase,
+            cues: VecDeque::new(),
+        }
+    }
+
+    /// Get the timebase that cue times are counted in.
+    pub fn timebase(&self) -> Timebase {
+        self.timebase
+    }
+
+    /// Add a cue, keeping cues sorted by start time.  Its times are
+    /// converted to this buffer's timebase.
+    pub fn add(&mut self, mut cue: Cue) {
+        cue.start = cue.start.rescale(self.timebase);
+        cue.end = cue.end.rescale(self.timebase);
+        let i = self
+            .cues
+            .iter()
+            .rposition(|c| c.start.ticks <= cue.start.ticks)
+            .map_or(0, |i| i + 1);
+        self.cues.insert(i, cue);
+    }
+
+    /// Remove and return the first cue.
+    pub fn pop(&mut self) -> Option<Cue> {
+        self.cues.pop_front()
+    }
+
+    /// Get the number of cues.
+    pub fn len(&self) -> usize {
+        self.cues.len()
+    }
+
+    /// Return true if there are no cues.
+    pub fn is_empty(&self) -> bool {
+        self.cues.is_empty()
+    }
+
+    /// Get a cue.
+    pub fn get(&self, i: usize) -> Option<&Cue> {
+        self.cues.get(i)
+    }
+
+    /// Iterate over the cues.
+    pub fn iter(&self) -> vec_deque::Iter<'_, Cue> {
+        self.cues.iter()
+    }
+
+    /// Get the cues that are showing at a time.
+    pub fn at(&self, time: Timestamp) -> impl Iterator<Item = &Cue> {
+        let ticks = time.rescale(self.timebase).ticks;
+        self.cues
+            .iter()
+            .take_while(move |c| c.start.ticks <= ticks)
+            .filter(move |c| ticks < c.end.ticks)
+    }
+
+    /// Get the cues that are showing during a video frame (at its start).
+    pub fn at_index(&self, index: Index) -> impl Iterator<Item = &Cue> {
+        self.at(index.to_timestamp())
+    }
+}
+
+impl<'a> IntoIterator for &'a Subtitles {
+    type Item = &'a Cue;
+    type IntoIter = vec_deque::Iter<'a, Cue>;
+
+    fn into_iter(self) -> vec_deque::Iter<'a, Cue> {
+        self.cues.iter()
+    }
+}
+
+/// Parse a `[HH:]MM:SS.mmm` (or `,mmm`) time into milliseconds.  Minutes
+/// and seconds must be under 60, there are no signs, and the milliseconds
+/// must fit in an `i64`.
+fn parse_time(text: &str) -> Option<i64> {
+    let number = |s: &str| -> Option<i64> {
+        if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        s.parse().ok()
+    };
+    let parts: Vec<&str> = text.trim().split(':').collect();
+    let (h, m, s) = match parts[..] {
+        [h, m, s] => (number(h)?, m, s),
+        [m, s] => (0, m, s),
+        _ => return None,
+    };
+    let m = number(m)?;
+    let mut s = s.splitn(2, ['.', ',']);
+    let (secs, ms) = (number(s.next()?)?, s.next()?);
+    if ms.len() != 3 || m >= 60 || secs >= 60 {
+        return None;
+    }
+    // Hours aren't limited, so check for overflow.
+    h.checked_mul(3_600_000)?
+        .checked_add((m * 60 + secs) * 1000 + number(ms)?)
+}
+
+/// Write a time as `HH:MM:SS.mmm`, with `sep` before the milliseconds.
+fn write_time(time: Timestamp, sep: char) -> String {
+    let ms = time.rescale(Timebase::MILLIS).ticks.max(0);
+    let (s, ms) = (ms / 1000, ms % 1000);
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        s / 3600,
+        s / 60 % 60,
+        s % 60,
+        sep,
+        ms
+    )
+}
+
+/// Parse `#rrggbb` into a color.
+fn parse_color(text: &str) -> Option<[u8; 3]> {
+    let hex = text
+        .trim_matches(|c| c == '"' || c == '\'')
+        .strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let n = u32::from_str_radix(hex, 16).ok()?;
+    Some([(n >> 16) as u8, (n >> 8) as u8, n as u8])
+}
+
+/// Parse text with `<b>`, `<i>`, `<u>` and `<font color="#rrggbb">` tags
+/// into spans.  Other tags are dropped (keeping their text).  If `entities`
+/// is true, `&amp;`, `&lt;`, etc. are decoded.
+fn parse_markup(text: &str, entities: bool) -> Vec<Span> {
+    let mut spans: Vec<Span> = Vec::new();
+    let mut stack = Vec::new();
+    let mut style = Style::default();
+    let push = |spans: &mut Vec<Span>, text: &str, style: Style| match spans.last_mut() {
+        Some(last) if last.style == style => last.text.push_str(text),
+        _ if text.is_empty() => {}
+        _ => spans.push(Span::new(text, style)),
+    };
+
+    let mut rest = text;
+    while !rest.is_empty() {
+        let special = rest
+            .find(|c| c == '<' || (entities && c == '&'))
+            .unwrap_or(rest.len());
+        push(&mut spans, &rest[..special], style);
+        rest = &rest[special..];
+        if rest.starts_with('&') {
+            let entity = rest.find(';').map(|end| (&rest[1..end], end));
+            let decoded = match entity {
+                Some(("amp", _)) => "&",
+                Some(("lt", _)) => "<",
+                Some(("gt", _)) => ">",
+                Some(("nbsp", _)) => "\u{a0}",
+                Some(("lrm", _)) => "\u{200e}",
+                Some(("rlm", _)) => "\u{200f}",
+                _ => {
+                    push(&mut spans, "&", style);
+                    rest = &rest[1..];
+                    continue;
+                }
+            };
+            push(&mut spans, decoded, style);
+            rest = &rest[entity.unwrap().1 + 1..];
+        } else if let Some(end) = rest.find('>') {
+            let tag = rest[1..end].trim();
+            rest = &rest[end + 1..];
+            if let Some(tag) = tag.strip_prefix('/') {
+                if !tag.is_empty() {
+                    style = stack.pop().unwrap_or_default();
+                }
+                continue;
+            }
+            // Timestamp tags (WebVTT karaoke) don't open anything.
+            if tag.starts_with(|c: char| c.is_ascii_digit()) {
+                continue;
+            }
+            stack.push(style);
+            let name_end = tag.find([' ', '.']).unwrap_or(tag.len());
+            match tag[..name_end].to_ascii_lowercase().as_str() {
+                "b" => style.bold = true,
+                "i" => style.italic = true,
+                "u" => style.underline = true,
+                "font" => {
+                    let lower = tag.to_ascii_lowercase();
+                    if let Some(at) = lower.find("color=") {
+                        let value = tag[at + 6..].split_whitespace().next();
+                        style.color = value.and_then(parse_color).or(style.color);
+                    }
+                }
+                _ => {}
+            }
+        } else {
+            push(&mut spans, rest, style);
+            break;
+        }
+    }
+    spans
+}
+
+/// Write spans with `<b>`, `<i>`, `<u>` (and `<font color>` if `color`)
+/// tags.  If `escape` is true, `&`, `<` and `>` are escaped.
+fn write_markup(spans: &[Span], color: bool, escape: bool) -> String {
+    let mut out = String::new();
+    for span in spans {
+        let style = span.style;
+        let mut close = Vec::new();
+        for &(on, tag) in &[
+            (style.bold, "b"),
+            (style.italic, "i"),
+            (style.underline, "u"),
+        ] {
+            if on {
+                out.push_str(&format!("<{}>", tag));
+                close.push(tag);
+            }
+        }
+        if let (true, Some([r, g, b])) = (color, style.color) {
+            out.push_str(&format!("<font color=\"#{:02x}{:02x}{:02x}\">", r, g, b));
+            close.push("font");
+        }
+        if escape {
+            for c in span.text.chars() {
+                match c {
+                    '&' => out.push_str("&amp;"),
+                    '<' => out.push_str("&lt;"),
+                    '>' => out.push_str("&gt;"),
+                    c => out.push(c),
+                }
+            }
+        } else {
+            out.push_str(&span.text);
+        }
+        for tag in close.iter().rev() {
+            out.push_str(&format!("</{}>", tag));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cues_at() {
+        let mut subtitles = Subtitles::new(Timebase::MILLIS);
+        let s = |s| Timestamp::from_seconds(s, Timebase::FPS_24);
+        subtitles.add(Cue::new(s(2.0), s(4.0), "second"));
+        subtitles.add(Cue::new(s(1.0), s(3.0), "first"));
+        assert_eq!(subtitles.get(0).unwrap().start.ticks, 1000);
+        let at = |frame| {
+            let index = Index::new(frame, Timebase::FPS_24);
+            subtitles.at_index(index).map(Cue::text).collect::<Vec<_>>()
+        };
+        assert_eq!(at(60), vec!["first", "second"]);
+        assert_eq!(at(72), vec!["second"]);
+        assert!(at(96).is_empty());
+        assert_eq!(subtitles.pop().unwrap().text(), "first");
+        assert_eq!(subtitles.len(), 1);
+    }
+
+    #[test]
+    fn times() {
+        assert_eq!(parse_time("01:02:03.004"), Some(3_723_004));
+        assert_eq!(parse_time("59:59,999"), Some(3_599_999));
+        for bad in &[
+            "00:-1:00.000",
+            "-1:00.000",
+            "00:60.000",
+            "01:60:00.000",
+            "00:+5.000",
+            "999999999999999999:00:00.000",
+            "99999999999999999999:00:00.000",
+        ] {
+            assert_eq!(parse_time(bad), None);
+        }
+    }
+
+    #[test]
+    fn markup() {
+        let spans = parse_markup("a <b>b <i>c</i></b> &lt;d&gt; <font color=#ff0000>e", true);
+        let text: Vec<&str> = spans.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(text, vec!["a ", "b ", "c", " <d> ", "e"]);
+        assert!(spans[2].style.bold && spans[2].style.italic);
+        assert_eq!(spans[4].style.color, Some([255, 0, 0]));
+        assert_eq!(
+            write_markup(&spans, true, true),
+            "a <b>b </b><b><i>c</i></b> &lt;d&gt; <font color=\"#ff0000\">e</font>"
+        );
+    }
+}
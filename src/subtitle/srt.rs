@@ -0,0 +1,167 @@
+// Copyright Jeron Lau 2017 - 2018.
+// Dual-licensed under either the MIT License or the Boost Software License, Version 1.0.
+// (See accompanying file LICENSE_1_0.txt or copy at https://www.boost.org/LICENSE_1_0.txt)
+
+use super::{parse_markup, parse_time, write_markup, write_time};
+use super::{Align, Cue, Position, Subtitles};
+use Error;
+use Result;
+use Timebase;
+use Timestamp;
+
+/// SubRip (.srt) subtitles.
+pub struct Srt;
+
+impl Srt {
+    /// Read subtitles from SubRip text.  Cue times are in `Timebase::MILLIS`.
+    pub fn read(text: &str) -> Result<Subtitles> {
+        let text = text.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+        let mut subtitles = Subtitles::new(Timebase::MILLIS);
+        for block in text.split("\n\n") {
+            let mut lines = block.lines().skip_while(|l| l.trim().is_empty());
+            let mut line = match lines.next() {
+                Some(line) => line,
+                None => continue,
+            };
+            let mut id = None;
+            if !line.contains("-->") {
+                id = Some(line.trim().to_string());
+                line = lines.next().unwrap_or("");
+            }
+            let (start, end) = timing(line)?;
+            let mut body = lines.collect::<Vec<_>>().join("\n");
+            let position = take_position(&mut body);
+            subtitles.add(Cue {
+                id,
+                start,
+                end,
+                spans: parse_markup(&body, false),
+                position,
+            });
+        }
+        Ok(subtitles)
+    }
+
+    /// Write subtitles as SubRip text.  Cues are numbered from 1.
+    pub fn write(subtitles: &Subtitles) -> String {
+        let mut out = String::new();
+        for (i, cue) in subtitles.iter().enumerate() {
+            out.push_str(&format!(
+                "{}\n{} --> {}\n{}{}\n\n",
+                i + 1,
+                write_time(cue.start, ','),
+                write_time(cue.end, ','),
+                write_position(&cue.position),
+                write_markup(&cue.spans, true, false),
+            ));
+        }
+        out
+    }
+}
+
+/// Parse a `start --> end` line.
+fn timing(line: &str) -> Result<(Timestamp, Timestamp)> {
+    let mut times = line.splitn(2, "-->");
+    let start = times.next().and_then(parse_time);
+    // Some files have coordinates after the end time.
+    let end = times
+        .next()
+        .and_then(|t| t.split_whitespace().next())
+        .and_then(parse_time);
+    match (start, end) {
+        (Some(start), Some(end)) => Ok((
+            Timestamp::new(start, Timebase::MILLIS),
+            Timestamp::new(end, Timebase::MILLIS),
+        )),
+        _ => Err(Error::Corrupt(format!("bad SRT timing {:?}", line))),
+    }
+}
+
+/// Remove a `{\anN}` (numpad position) tag from the text, and get the
+/// position it stands for.
+fn take_position(body: &mut String) -> Position {
+    let mut position = Position::default();
+    if let Some(at) = body.find("{\\an") {
+        let n = body[at + 4..].chars().next().and_then(|c| c.to_digit(10));
+        if let (Some(n @ 1..=9), Some(b'}')) = (n, body.as_bytes().get(at + 5)) {
+            position.y = match n {
+                7..=9 => Some(0.0),
+                4..=6 => Some(0.5),
+                _ => None,
+            };
+            position.align = match n % 3 {
+                1 => Align::Start,
+                2 => Align::Center,
+                _ => Align::End,
+            };
+            body.replace_range(at..at + 6, "");
+        }
+    }
+    position
+}
+
+/// Write a `{\anN}` tag for a position, if it isn't the default.
+fn write_position(position: &Position) -> String {
+    let row = match position.y {
+        Some(y) if y < 1.0 / 3.0 => 7,
+        Some(y) if y < 2.0 / 3.0 => 4,
+        _ => 1,
+    };
+    let column = match position.align {
+        Align::Start => 0,
+        Align::Center => 1,
+        Align::End => 2,
+    };
+    match row + column {
+        2 => String::new(),
+        n => format!("{{\\an{}}}", n),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use subtitle::{Span, Style};
+
+    #[test]
+    fn round_trip() {
+        let text = "\u{feff}1\r\n00:00:01,500 --> 00:00:03,000\r\n{\\an8}Hello <b>bold</b>\r\n\
+                    <font color=\"#00ff00\">green</font>\r\n\r\n\
+                    2\r\n00:01:00,000 --> 01:00:00,001\r\n<i>x</i>\r\n";
+        let subtitles = Srt::read(text).unwrap();
+        assert_eq!(subtitles.len(), 2);
+        let cue = subtitles.get(0).unwrap();
+        assert_eq!(cue.id.as_deref(), Some("1"));
+        assert_eq!((cue.start.ticks, cue.end.ticks), (1500, 3000));
+        assert_eq!(cue.text(), "Hello bold\ngreen");
+        assert_eq!(
+            (cue.position.y, cue.position.align),
+            (Some(0.0), Align::Center)
+        );
+        let bold = Style {
+            bold: true,
+            ..Style::default()
+        };
+        assert_eq!(cue.spans[1], Span::new("bold", bold));
+        assert_eq!(cue.spans[3].style.color, Some([0, 255, 0]));
+        assert_eq!(subtitles.get(1).unwrap().end.ticks, 3_600_001);
+
+        let written = Srt::write(&subtitles);
+        assert!(written.starts_with("1\n00:00:01,500 --> 00:00:03,000\n{\\an8}Hello <b>bold</b>"));
+        let again = Srt::read(&written).unwrap();
+        assert_eq!(
+            again.iter().collect::<Vec<_>>(),
+            subtitles.iter().collect::<Vec<_>>()
+        );
+
+        assert!(Srt::read("1\n00:00:01 --> 00:00:02\nx").is_err());
+        assert!(matches!(
+            Srt::read("1\n00:00:61,000 --> 00:01:02,000\nx"),
+            Err(Error::Corrupt(_))
+        ));
+        assert!(matches!(
+            Srt::read("1\n999999999999999999:00:00,000 --> 00:00:01,000\nx"),
+            Err(Error::Corrupt(_))
+        ));
+    }
+}
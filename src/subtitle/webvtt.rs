@@ -0,0 +1,170 @@
+// Copyright Jeron Lau 2017 - 2018.
+// Dual-licensed under either the MIT License or the Boost Software License, Version 1.0.
+// (See accompanying file LICENSE_1_0.txt or copy at https://www.boost.org/LICENSE_1_0.txt)
+
+use super::{parse_markup, parse_time, write_markup, write_time};
+use super::{Align, Cue, Position, Subtitles};
+use Error;
+use Result;
+use Timebase;
+use Timestamp;
+
+/// WebVTT (.vtt) subtitles.
+pub struct WebVtt;
+
+impl WebVtt {
+    /// Read subtitles from WebVTT text.  Cue times are in
+    /// `Timebase::MILLIS`.  Fails with `Error::UnsupportedFormat` if there's
+    /// no `WEBVTT` header.
+    pub fn read(text: &str) -> Result<Subtitles> {
+        let text = text.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+        if !text.starts_with("WEBVTT") {
+            return Err(Error::UnsupportedFormat);
+        }
+        let mut subtitles = Subtitles::new(Timebase::MILLIS);
+        // The first block is the header.
+        for block in text.split("\n\n").skip(1) {
+            let mut lines = block.lines().skip_while(|l| l.trim().is_empty());
+            let mut line = match lines.next() {
+                Some(line) => line,
+                None => continue,
+            };
+            if ["NOTE", "STYLE", "REGION"]
+                .iter()
+                .any(|b| line.split_whitespace().next() == Some(b))
+            {
+                continue;
+            }
+            let mut id = None;
+            if !line.contains("-->") {
+                id = Some(line.trim().to_string());
+                line = lines.next().unwrap_or("");
+            }
+            let mut times = line.splitn(2, "-->");
+            let start = times.next().and_then(parse_time);
+            let mut rest = times.next().unwrap_or("").split_whitespace();
+            let end = rest.next().and_then(parse_time);
+            let (start, end) = match (start, end) {
+                (Some(start), Some(end)) => (start, end),
+                _ => return Err(Error::Corrupt(format!("bad WebVTT timing {:?}", line))),
+            };
+            let body = lines.collect::<Vec<_>>().join("\n");
+            subtitles.add(Cue {
+                id,
+                start: Timestamp::new(start, Timebase::MILLIS),
+                end: Timestamp::new(end, Timebase::MILLIS),
+                spans: parse_markup(&body, true),
+                position: settings(rest),
+            });
+        }
+        Ok(subtitles)
+    }
+
+    /// Write subtitles as WebVTT text.
+    pub fn write(subtitles: &Subtitles) -> String {
+        let mut out = String::from("WEBVTT\n\n");
+        for cue in subtitles {
+            if let Some(ref id) = cue.id {
+                out.push_str(id);
+                out.push('\n');
+            }
+            out.push_str(&format!(
+                "{} --> {}{}\n{}\n\n",
+                write_time(cue.start, '.'),
+                write_time(cue.end, '.'),
+                write_settings(&cue.position),
+                write_markup(&cue.spans, false, true),
+            ));
+        }
+        out
+    }
+}
+
+/// Parse a percentage, like `50%`, into 0.0 to 1.0.
+fn percent(value: &str) -> Option<f32> {
+    let value = value.split(',').next()?.strip_suffix('%')?;
+    value.parse::<f32>().ok().map(|p| p / 100.0)
+}
+
+/// Parse cue settings (`position:`, `line:` and `align:`).
+fn settings<'a, I: Iterator<Item = &'a str>>(settings: I) -> Position {
+    let mut position = Position::default();
+    for setting in settings {
+        let mut setting = setting.splitn(2, ':');
+        match (setting.next(), setting.next()) {
+            (Some("position"), Some(value)) => position.x = percent(value),
+            (Some("line"), Some(value)) => position.y = percent(value),
+            (Some("align"), Some(value)) => {
+                position.align = match value {
+                    "start" | "left" => Align::Start,
+                    "end" | "right" => Align::End,
+                    _ => Align::Center,
+                }
+            }
+            _ => {}
+        }
+    }
+    position
+}
+
+/// Write cue settings for a position, with a space before each one.
+fn write_settings(position: &Position) -> String {
+    let mut out = String::new();
+    if let Some(x) = position.x {
+        out.push_str(&format!(" position:{}%", x * 100.0));
+    }
+    if let Some(y) = position.y {
+        out.push_str(&format!(" line:{}%", y * 100.0));
+    }
+    match position.align {
+        Align::Start => out.push_str(" align:start"),
+        Align::Center => {}
+        Align::End => out.push_str(" align:end"),
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_write() {
+        let text = "WEBVTT - Example\n\nNOTE a comment\n\nSTYLE\n::cue { color: red }\n\n\
+                    intro\n00:01.000 --> 00:02.500 position:10% line:0% align:left\n\
+                    <v Bob>Tom &amp; <i>Jerry</i> &lt;3</v>\n\n\
+                    00:00:03.000 --> 00:00:04.000\nmore\n";
+        let subtitles = WebVtt::read(text).unwrap();
+        assert_eq!(subtitles.len(), 2);
+        let cue = subtitles.get(0).unwrap();
+        assert_eq!(cue.id.as_deref(), Some("intro"));
+        assert_eq!((cue.start.ticks, cue.end.ticks), (1000, 2500));
+        assert_eq!(cue.text(), "Tom & Jerry <3");
+        assert!(cue.spans[1].style.italic);
+        assert_eq!(
+            cue.position,
+            Position {
+                x: Some(0.1),
+                y: Some(0.0),
+                align: Align::Start,
+            }
+        );
+
+        let written = WebVtt::write(&subtitles);
+        assert!(written.contains(
+            "intro\n00:00:01.000 --> 00:00:02.500 position:10% line:0% align:start\n\
+             Tom &amp; <i>Jerry</i> &lt;3\n"
+        ));
+        let again = WebVtt::read(&written).unwrap();
+        assert_eq!(
+            again.iter().collect::<Vec<_>>(),
+            subtitles.iter().collect::<Vec<_>>()
+        );
+
+        assert!(WebVtt::read("1\n00:00:01,000 --> 00:00:02,000\nx").is_err());
+        assert!(matches!(
+            WebVtt::read("WEBVTT\n\n999999999999999999:00:00.000 --> 00:00:01.000\nx"),
+            Err(Error::Corrupt(_))
+        ));
+    }
+}
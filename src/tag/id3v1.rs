@@ -0,0 +1,208 @@
+// Copyright Jeron Lau 2017 - 2018.
+// Dual-licensed under either the MIT License or the Boost Software License, Version 1.0.
+// (See accompanying file LICENSE_1_0.txt or copy at https://www.boost.org/LICENSE_1_0.txt)
+
+use super::{latin1, to_latin1};
+use Date;
+use Key;
+use Metadata;
+use Value;
+
+/// The ID3v1 genres.
+pub(crate) const GENRES: [&str; 80] = [
+    "Blues",
+    "Classic Rock",
+    "Country",
+    "Dance",
+    "Disco",
+    "Funk",
+    "Grunge",
+    "Hip-Hop",
+    "Jazz",
+    "Metal",
+    "New Age",
+    "Oldies",
+    "Other",
+    "Pop",
+    "R&B",
+    "Rap",
+    "Reggae",
+    "Rock",
+    "Techno",
+    "Industrial",
+    "Alternative",
+    "Ska",
+    "Death Metal",
+    "Pranks",
+    "Soundtrack",
+    "Euro-Techno",
+    "Ambient",
+    "Trip-Hop",
+    "Vocal",
+    "Jazz+Funk",
+    "Fusion",
+    "Trance",
+    "Classical",
+    "Instrumental",
+    "Acid",
+    "House",
+    "Game",
+    "Sound Clip",
+    "Gospel",
+    "Noise",
+    "AlternRock",
+    "Bass",
+    "Soul",
+    "Punk",
+    "Space",
+    "Meditative",
+    "Instrumental Pop",
+    "Instrumental Rock",
+    "Ethnic",
+    "Gothic",
+    "Darkwave",
+    "Techno-Industrial",
+    "Electronic",
+    "Pop-Folk",
+    "Eurodance",
+    "Dream",
+    "Southern Rock",
+    "Comedy",
+    "Cult",
+    "Gangsta",
+    "Top 40",
+    "Christian Rap",
+    "Pop/Funk",
+    "Jungle",
+    "Native American",
+    "Cabaret",
+    "New Wave",
+    "Psychadelic",
+    "Rave",
+    "Showtunes",
+    "Trailer",
+    "Lo-Fi",
+    "Tribal",
+    "Acid Punk",
+    "Acid Jazz",
+    "Polka",
+    "Retro",
+    "Musical",
+    "Rock & Roll",
+    "Hard Rock",
+];
+
+/// Decode a fixed-size, null / space padded text field.
+fn field(bytes: &[u8]) -> Option<String> {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    let text = latin1(&bytes[..end]);
+    let text = text.trim_end();
+    if text.is_empty() {
+        None
+    } else {
+        Some(text.to_string())
+    }
+}
+
+/// Encode a fixed-size, null padded text field.
+fn set_field(out: &mut [u8], value: Option<&Value>) {
+    if let Some(value) = value {
+        let text = to_latin1(&value.to_string());
+        let len = text.len().min(out.len());
+        out[..len].copy_from_slice(&text[..len]);
+    }
+}
+
+/// ID3v1 / ID3v1.1 tags: the 128 bytes at the end of an MP3 file.
+pub struct Id3v1;
+
+impl Id3v1 {
+    /// The size of an ID3v1 tag.
+    pub const SIZE: usize = 128;
+
+    /// Read the ID3v1 tag at the end of `data`.  Returns `None` if there
+    /// isn't one.
+    pub fn read(data: &[u8]) -> Option<Metadata> {
+        if data.len() < Id3v1::SIZE {
+            return None;
+        }
+        let tag = &data[data.len() - Id3v1::SIZE..];
+        if &tag[..3] != b"TAG" {
+            return None;
+        }
+
+        let mut metadata = Metadata::new();
+        let mut add = |key: Key, text: Option<String>| {
+            if let Some(text) = text {
+                metadata.add(key, text);
+            }
+        };
+        add(Key::Title, field(&tag[3..33]));
+        add(Key::Artist, field(&tag[33..63]));
+        add(Key::Album, field(&tag[63..93]));
+        if let Some(year) = field(&tag[93..97]).and_then(|y| Date::parse(&y)) {
+            metadata.add(Key::Release, year);
+        }
+        // ID3v1.1 puts the track number in the last byte of the comment.
+        let v11 = tag[125] == 0 && tag[126] != 0;
+        let comment = if v11 { &tag[97..125] } else { &tag[97..127] };
+        if let Some(comment) = field(comment) {
+            metadata.add(Key::Comment, comment);
+        }
+        if v11 {
+            metadata.add(Key::TrackNumber, tag[126] as i64);
+        }
+        if let Some(genre) = GENRES.get(tag[127] as usize) {
+            metadata.add(Key::Genre, *genre);
+        }
+
+        Some(metadata)
+    }
+
+    /// Write an ID3v1.1 tag, to be appended to a file.  Text that is too long
+    /// is cut off.
+    pub fn write(metadata: &Metadata) -> Vec<u8> {
+        let mut tag = vec![0; Id3v1::SIZE];
+        tag[..3].copy_from_slice(b"TAG");
+        set_field(&mut tag[3..33], metadata.get(&Key::Title));
+        set_field(&mut tag[33..63], metadata.get(&Key::Artist));
+        set_field(&mut tag[63..93], metadata.get(&Key::Album));
+        if let Some(date) = metadata.date(&Key::Release) {
+            set_field(&mut tag[93..97], Some(&Value::Date(Date::year(date.year))));
+        }
+        set_field(&mut tag[97..125], metadata.get(&Key::Comment));
+        tag[126] = metadata
+            .integer(&Key::TrackNumber)
+            .filter(|n| *n > 0 && *n < 256)
+            .unwrap_or(0) as u8;
+        tag[127] = metadata
+            .text(&Key::Genre)
+            .and_then(|g| GENRES.iter().position(|n| n.eq_ignore_ascii_case(g)))
+            .unwrap_or(255) as u8;
+        tag
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut metadata = Metadata::new();
+        metadata.add(Key::Title, "Song");
+        metadata.add(Key::Artist, "Band");
+        metadata.add(Key::Release, Date::new(1999, 1, 2));
+        metadata.add(Key::TrackNumber, 3i64);
+        metadata.add(Key::Genre, "rock");
+        let mut file = vec![0xFF; 10];
+        file.extend(Id3v1::write(&metadata));
+        let read = Id3v1::read(&file).unwrap();
+        assert_eq!(read.text(&Key::Title), Some("Song"));
+        assert_eq!(read.text(&Key::Artist), Some("Band"));
+        assert_eq!(read.get(&Key::Album), None);
+        assert_eq!(read.date(&Key::Release), Some(Date::year(1999)));
+        assert_eq!(read.integer(&Key::TrackNumber), Some(3));
+        assert_eq!(read.text(&Key::Genre), Some("Rock"));
+    }
+}
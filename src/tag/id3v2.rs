@@ -0,0 +1,567 @@
+// Copyright Jeron Lau 2017 - 2018.
+// Dual-licensed under either the MIT License or the Boost Software License, Version 1.0.
+// (See accompanying file LICENSE_1_0.txt or copy at https://www.boost.org/LICENSE_1_0.txt)
+
+use super::id3v1::GENRES;
+use super::{latin1, split_pair, to_latin1, typed};
+use Date;
+use Error;
+use Key;
+use Metadata;
+use Picture;
+use PictureKind;
+use Result;
+use Value;
+
+/// Text frames that map directly to a key.
+const TEXT_FRAMES: [(&[u8; 4], Key); 9] = [
+    (b"TIT2", Key::Title),
+    (b"TPE1", Key::Artist),
+    (b"TPE2", Key::AlbumArtist),
+    (b"TALB", Key::Album),
+    (b"TCON", Key::Genre),
+    (b"TCOM", Key::Composer),
+    (b"TOPE", Key::OriginalArtist),
+    (b"TCOP", Key::Copyright),
+    (b"TENC", Key::EncodedBy),
+];
+
+/// Read a 28-bit "synchsafe" integer (7 bits per byte).
+fn synchsafe(b: &[u8]) -> Result<usize> {
+    if b[..4].iter().any(|&b| b & 0x80 != 0) {
+        return Err(Error::Corrupt("invalid synchsafe integer".to_string()));
+    }
+    Ok((b[0] as usize) << 21 | (b[1] as usize) << 14 | (b[2] as usize) << 7 | b[3] as usize)
+}
+
+/// Write a 28-bit "synchsafe" integer (7 bits per byte).
+fn to_synchsafe(n: usize) -> [u8; 4] {
+    assert!(n < 1 << 28, "ID3v2 tag is too big");
+    [
+        (n >> 21) as u8 & 0x7F,
+        (n >> 14) as u8 & 0x7F,
+        (n >> 7) as u8 & 0x7F,
+        n as u8 & 0x7F,
+    ]
+}
+
+/// Undo unsynchronisation (`FF 00` becomes `FF`).
+fn resync(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut last = 0;
+    for &b in data {
+        if !(last == 0xFF && b == 0x00) {
+            out.push(b);
+        }
+        last = b;
+    }
+    out
+}
+
+/// Length of the null terminator for a text encoding.
+fn terminator(encoding: u8) -> usize {
+    match encoding {
+        1 | 2 => 2,
+        _ => 1,
+    }
+}
+
+/// Split null-terminated text off the front of `data`.
+fn split_text(encoding: u8, data: &[u8]) -> (&[u8], &[u8]) {
+    let step = terminator(encoding);
+    let mut i = 0;
+    while i + step <= data.len() {
+        if data[i..i + step].iter().all(|&b| b == 0) {
+            return (&data[..i], &data[i + step..]);
+        }
+        i += step;
+    }
+    (data, &[])
+}
+
+/// Decode text in an ID3v2 text encoding.
+fn decode(encoding: u8, data: &[u8]) -> String {
+    let utf16 = |data: &[u8], big: bool| {
+        let units: Vec<u16> = data
+            .chunks(2)
+            .filter(|c| c.len() == 2)
+            .map(|c| {
+                if big {
+                    (c[0] as u16) << 8 | c[1] as u16
+                } else {
+                    (c[1] as u16) << 8 | c[0] as u16
+                }
+            })
+            .collect();
+        String::from_utf16_lossy(&units)
+    };
+    let text = match encoding {
+        0 => latin1(data),
+        1 => match data.get(..2) {
+            Some([0xFE, 0xFF]) => utf16(&data[2..], true),
+            Some([0xFF, 0xFE]) => utf16(&data[2..], false),
+            _ => utf16(data, false),
+        },
+        2 => utf16(data, true),
+        _ => String::from_utf8_lossy(data).into_owned(),
+    };
+    text.trim_end_matches('\0').to_string()
+}
+
+/// Encode text in an ID3v2 text encoding.
+fn encode(encoding: u8, text: &str) -> Vec<u8> {
+    match encoding {
+        0 => to_latin1(text),
+        1 => {
+            let mut out = vec![0xFF, 0xFE];
+            for unit in text.encode_utf16() {
+                out.extend_from_slice(&[unit as u8, (unit >> 8) as u8]);
+            }
+            out
+        }
+        _ => text.as_bytes().to_vec(),
+    }
+}
+
+/// Get a genre name from a TCON value, like "(17)", "17" or "(17)Rock".
+fn genre(text: &str) -> String {
+    let name = |n: &str| n.parse::<usize>().ok().and_then(|n| GENRES.get(n).cloned());
+    if text.starts_with('(') {
+        if let Some(end) = text.find(')') {
+            let rest = &text[end + 1..];
+            if !rest.is_empty() {
+                return rest.to_string();
+            }
+            if let Some(genre) = name(&text[1..end]) {
+                return genre.to_string();
+            }
+        }
+    } else if let Some(genre) = name(text) {
+        return genre.to_string();
+    }
+    text.to_string()
+}
+
+/// ID3v2.3 and ID3v2.4 tags: found at the start of MP3 (and other) files.
+pub struct Id3v2;
+
+impl Id3v2 {
+    /// Get the total size of the ID3v2 tag at the start of `data` (including
+    /// the header and footer), so it can be skipped.  Returns `None` if
+    /// there isn't one.  Only the first 10 bytes are needed.
+    pub fn size(data: &[u8]) -> Option<usize> {
+        if data.len() < 10 || &data[..3] != b"ID3" {
+            return None;
+        }
+        let footer = if data[3] == 4 && data[5] & 0x10 != 0 {
+            10
+        } else {
+            0
+        };
+        synchsafe(&data[6..10]).ok().map(|size| 10 + size + footer)
+    }
+
+    /// Read the ID3v2.3 or ID3v2.4 tag at the start of `data`.  Fails with
+    /// `Error::UnsupportedFormat` if there isn't one.
+    pub fn read(data: &[u8]) -> Result<Metadata> {
+        if data.len() < 10 || &data[..3] != b"ID3" {
+            return Err(Error::UnsupportedFormat);
+        }
+        let version = data[3];
+        if version != 3 && version != 4 {
+            return Err(Error::UnsupportedFeature(format!("ID3v2.{}", version)));
+        }
+        let flags = data[5];
+        let size = synchsafe(&data[6..10])?;
+        let body = data.get(10..10 + size).ok_or(Error::UnexpectedEof)?;
+        let body = if version == 3 && flags & 0x80 != 0 {
+            resync(body)
+        } else {
+            body.to_vec()
+        };
+
+        let mut pos = 0;
+        if flags & 0x40 != 0 {
+            let ext = body.get(..4).ok_or(Error::UnexpectedEof)?;
+            pos = if version == 3 {
+                4 + u32::from_be_bytes([ext[0], ext[1], ext[2], ext[3]]) as usize
+            } else {
+                synchsafe(ext)?
+            };
+        }
+
+        let mut metadata = Metadata::new();
+        let mut date = None;
+        let mut day_month = None;
+        while pos + 10 <= body.len() && body[pos] != 0 {
+            let header = &body[pos..pos + 10];
+            let id = &header[..4];
+            let size = if version == 3 {
+                u32::from_be_bytes([header[4], header[5], header[6], header[7]]) as usize
+            } else {
+                synchsafe(&header[4..8])?
+            };
+            let frame = body
+                .get(pos + 10..pos + 10 + size)
+                .ok_or(Error::UnexpectedEof)?;
+            pos += 10 + size;
+
+            // Skip compressed and encrypted frames, and extra frame data.
+            let flags = header[9];
+            let frame = if version == 3 {
+                if flags & 0xC0 != 0 {
+                    continue;
+                }
+                let skip = (flags & 0x20 != 0) as usize;
+                frame.get(skip..).ok_or(Error::UnexpectedEof)?
+            } else {
+                if flags & 0x0C != 0 {
+                    continue;
+                }
+                let skip = (flags & 0x40 != 0) as usize + 4 * (flags & 0x01 != 0) as usize;
+                frame.get(skip..).ok_or(Error::UnexpectedEof)?
+            };
+            let frame = if version == 4 && (flags & 0x02 != 0 || data[5] & 0x80 != 0) {
+                resync(frame)
+            } else {
+                frame.to_vec()
+            };
+            let (&encoding, text) = match frame.split_first() {
+                Some(split) => split,
+                None => continue,
+            };
+
+            // Text frames can have multiple values, split by nulls in 2.4.
+            let values = || -> Vec<String> {
+                decode(encoding, text)
+                    .split('\0')
+                    .filter(|v| !v.is_empty())
+                    .map(|v| v.to_string())
+                    .collect()
+            };
+
+            if let Some((_, key)) = TEXT_FRAMES.iter().find(|f| &f.0[..] == id) {
+                for value in values() {
+                    let value = if *key == Key::Genre {
+                        genre(&value)
+                    } else {
+                        value
+                    };
+                    metadata.add(key.clone(), value);
+                }
+                continue;
+            }
+
+            match id {
+                b"TRCK" | b"TPOS" => {
+                    let (number, count) = if id == b"TRCK" {
+                        (Key::TrackNumber, Key::TrackCount)
+                    } else {
+                        (Key::Disc, Key::DiscCount)
+                    };
+                    if let Some(value) = values().first() {
+                        let (n, c) = split_pair(value);
+                        metadata.add(number.clone(), typed(&number, n));
+                        if let Some(c) = c {
+                            metadata.add(count.clone(), typed(&count, c));
+                        }
+                    }
+                }
+                b"TYER" | b"TDRC" => date = values().first().and_then(|d| Date::parse(d)),
+                b"TDAT" => day_month = values().first().cloned(),
+                b"TXXX" => {
+                    let (name, value) = split_text(encoding, text);
+                    let name = decode(encoding, name);
+                    let key = Key::new(&name);
+                    for value in decode(encoding, value).split('\0') {
+                        metadata.add(key.clone(), typed(&key, value));
+                    }
+                }
+                b"COMM" => {
+                    let (desc, comment) =
+                        split_text(encoding, text.get(3..).ok_or(Error::UnexpectedEof)?);
+                    if desc.is_empty() {
+                        metadata.add(Key::Comment, decode(encoding, comment));
+                    }
+                }
+                b"WOAR" => metadata.add(Key::Url, latin1(&frame).trim_end_matches('\0')),
+                b"APIC" => {
+                    let (mime, rest) = split_text(0, text);
+                    let (&kind, rest) = rest.split_first().ok_or(Error::UnexpectedEof)?;
+                    let (description, data) = split_text(encoding, rest);
+                    metadata.add(
+                        Key::Picture,
+                        Picture {
+                            kind: PictureKind::from_u8(kind),
+                            mime: latin1(mime),
+                            description: decode(encoding, description),
+                            data: data.to_vec(),
+                        },
+                    );
+                }
+                _ if id[0] == b'T' => {
+                    let key = Key::Custom(latin1(id));
+                    for value in values() {
+                        metadata.add(key.clone(), value);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(mut date) = date {
+            // ID3v2.3 stores the day and month separately, as "DDMM".
+            let digits = |dm: &String| dm.len() == 4 && dm.bytes().all(|b| b.is_ascii_digit());
+            if let Some(dm) = day_month.filter(digits) {
+                date.day = dm[..2].parse().ok();
+                date.month = dm[2..].parse().ok();
+            }
+            metadata.add(Key::Release, date);
+        }
+
+        Ok(metadata)
+    }
+
+    /// Write an ID3v2 tag, to be put at the start of a file.  `version` is
+    /// either 3 (ID3v2.3, more compatible) or 4 (ID3v2.4, UTF-8 text).
+    pub fn write(metadata: &Metadata, version: u8) -> Vec<u8> {
+        assert!(version == 3 || version == 4, "ID3v2 version must be 3 or 4");
+
+        let mut frames = Vec::new();
+        let mut frame = |id: &[u8], body: Vec<u8>| {
+            frames.extend_from_slice(id);
+            if version == 3 {
+                frames.extend_from_slice(&(body.len() as u32).to_be_bytes());
+            } else {
+                frames.extend_from_slice(&to_synchsafe(body.len()));
+            }
+            frames.extend_from_slice(&[0, 0]);
+            frames.extend(body);
+        };
+        // UTF-8 for 2.4, Latin-1 or UTF-16 for 2.3.
+        let encoding = |texts: &[&str]| -> u8 {
+            if version == 4 {
+                3
+            } else if texts.iter().all(|t| t.chars().all(|c| (c as u32) < 256)) {
+                0
+            } else {
+                1
+            }
+        };
+        let separator = if version == 4 { "\0" } else { "/" };
+        let text_frame = |values: &[String]| -> Vec<u8> {
+            let text = values.join(separator);
+            let encoding = encoding(&[&text]);
+            let mut body = vec![encoding];
+            body.extend(encode(encoding, &text));
+            body
+        };
+
+        // Keys in the order they first appear.
+        let mut keys: Vec<&Key> = Vec::new();
+        for (key, _) in metadata {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+
+        for key in keys {
+            let values: Vec<&Value> = metadata.get_all(key).collect();
+            let texts: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+            if let Some(&(id, _)) = TEXT_FRAMES.iter().find(|f| f.1 == *key) {
+                frame(id, text_frame(&texts));
+                continue;
+            }
+            match *key {
+                Key::TrackNumber | Key::TrackCount | Key::Disc | Key::DiscCount => {
+                    let (id, number, count) = match *key {
+                        Key::TrackNumber | Key::TrackCount => {
+                            (b"TRCK", Key::TrackNumber, Key::TrackCount)
+                        }
+                        _ => (b"TPOS", Key::Disc, Key::DiscCount),
+                    };
+                    // Written once, for whichever of the pair comes first.
+                    if *key == count && metadata.get(&number).is_some() {
+                        continue;
+                    }
+                    let number = metadata.get(&number).map(|v| v.to_string());
+                    let pair = match (number, metadata.get(&count)) {
+                        (Some(n), Some(c)) => format!("{}/{}", n, c),
+                        (Some(n), None) => n,
+                        (None, Some(c)) => format!("0/{}", c),
+                        (None, None) => unreachable!(),
+                    };
+                    frame(id, text_frame(&[pair]));
+                }
+                Key::Release => {
+                    let date = match values[0].as_date() {
+                        Some(date) => date,
+                        None => continue,
+                    };
+                    if version == 4 {
+                        frame(b"TDRC", text_frame(&[date.to_string()]));
+                    } else {
+                        frame(b"TYER", text_frame(&[format!("{:04}", date.year)]));
+                        if let (Some(m), Some(d)) = (date.month, date.day) {
+                            frame(b"TDAT", text_frame(&[format!("{:02}{:02}", d, m)]));
+                        }
+                    }
+                }
+                Key::Comment => {
+                    for text in &texts {
+                        let encoding = encoding(&[text]);
+                        let mut body = vec![encoding];
+                        body.extend_from_slice(b"eng");
+                        body.extend(encode(encoding, ""));
+                        body.extend(vec![0; terminator(encoding)]);
+                        body.extend(encode(encoding, text));
+                        frame(b"COMM", body);
+                    }
+                }
+                Key::Url => frame(b"WOAR", to_latin1(&texts[0])),
+                Key::Picture => {
+                    for picture in values.iter().filter_map(|v| v.as_picture()) {
+                        let encoding = encoding(&[&picture.description]);
+                        let mut body = vec![encoding];
+                        body.extend(to_latin1(&picture.mime));
+                        body.push(0);
+                        body.push(picture.kind as u8);
+                        body.extend(encode(encoding, &picture.description));
+                        body.extend(vec![0; terminator(encoding)]);
+                        body.extend_from_slice(&picture.data);
+                        frame(b"APIC", body);
+                    }
+                }
+                Key::Custom(ref name) => {
+                    let id = name.as_bytes();
+                    let is_frame = id.len() == 4
+                        && id[0] == b'T'
+                        && id != b"TXXX"
+                        && id
+                            .iter()
+                            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit());
+                    if is_frame {
+                        frame(id, text_frame(&texts));
+                    } else {
+                        let text = texts.join(separator);
+                        let encoding = encoding(&[name, &text]);
+                        let mut body = vec![encoding];
+                        body.extend(encode(encoding, name));
+                        body.extend(vec![0; terminator(encoding)]);
+                        body.extend(encode(encoding, &text));
+                        frame(b"TXXX", body);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut tag = b"ID3".to_vec();
+        tag.extend_from_slice(&[version, 0, 0]);
+        tag.extend_from_slice(&to_synchsafe(frames.len()));
+        tag.extend(frames);
+        tag
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Metadata {
+        let mut metadata = Metadata::new();
+        metadata.add(Key::Title, "Ünïcode ♫");
+        metadata.add(Key::Artist, "One");
+        metadata.add(Key::Artist, "Two");
+        metadata.add(Key::TrackNumber, 3i64);
+        metadata.add(Key::TrackCount, 12i64);
+        metadata.add(Key::Release, Date::new(2018, 5, 6));
+        metadata.add(Key::Comment, "Nice");
+        metadata.add(Key::new("replaygain_track_gain"), "-6.5 dB");
+        metadata.add(
+            Key::Picture,
+            Picture {
+                kind: PictureKind::FrontCover,
+                mime: "image/png".to_string(),
+                description: "Cover".to_string(),
+                data: vec![0x89, b'P', b'N', b'G', 0xFF, 0x00],
+            },
+        );
+        metadata
+    }
+
+    #[test]
+    fn round_trip() {
+        for &version in &[3, 4] {
+            let tag = Id3v2::write(&sample(), version);
+            assert_eq!(Id3v2::size(&tag), Some(tag.len()));
+            let read = Id3v2::read(&tag).unwrap();
+            assert_eq!(read.text(&Key::Title), Some("Ünïcode ♫"));
+            assert_eq!(read.integer(&Key::TrackNumber), Some(3));
+            assert_eq!(read.integer(&Key::TrackCount), Some(12));
+            assert_eq!(read.date(&Key::Release), Some(Date::new(2018, 5, 6)));
+            assert_eq!(read.text(&Key::Comment), Some("Nice"));
+            let gain = Key::Custom("REPLAYGAIN_TRACK_GAIN".to_string());
+            assert_eq!(read.text(&gain), Some("-6.5 dB"));
+            assert_eq!(read.pictures().next(), sample().pictures().next());
+        }
+        let artists = |version| {
+            let read = Id3v2::read(&Id3v2::write(&sample(), version)).unwrap();
+            read.get_all(&Key::Artist)
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(artists(4), vec!["One", "Two"]);
+        assert_eq!(artists(3), vec!["One/Two"]);
+    }
+
+    #[test]
+    fn errors() {
+        let tag = Id3v2::write(&sample(), 4);
+        assert!(matches!(
+            Id3v2::read(&tag[..tag.len() - 1]),
+            Err(Error::UnexpectedEof)
+        ));
+        assert!(matches!(
+            Id3v2::read(b"RIFF\0\0\0\0WAVE"),
+            Err(Error::UnsupportedFormat)
+        ));
+    }
+
+    #[test]
+    fn read_v23_unsynchronised() {
+        let mut tag = b"ID3\x03\x00\x80".to_vec();
+        let mut frames = Vec::new();
+        // TCON "(17)" in Latin-1.
+        frames.extend_from_slice(b"TCON\x00\x00\x00\x05\x00\x00\x00(17)");
+        // TIT2 "ÿ" in Latin-1, unsynchronised (FF -> FF 00).
+        frames.extend_from_slice(b"TIT2\x00\x00\x00\x02\x00\x00\x00\xFF\x00");
+        tag.extend_from_slice(&to_synchsafe(frames.len()));
+        tag.extend(frames);
+        let read = Id3v2::read(&tag).unwrap();
+        assert_eq!(read.text(&Key::Genre), Some("Rock"));
+        assert_eq!(read.text(&Key::Title), Some("ÿ"));
+    }
+
+    #[test]
+    fn malformed_v23() {
+        let tag = |frames: &[u8]| {
+            let mut tag = b"ID3\x03\x00\x00".to_vec();
+            tag.extend_from_slice(&to_synchsafe(frames.len()));
+            tag.extend_from_slice(frames);
+            tag
+        };
+        // Empty frame with the grouping flag (which needs a group byte).
+        assert!(matches!(
+            Id3v2::read(&tag(b"TIT2\x00\x00\x00\x00\x00\x20")),
+            Err(Error::UnexpectedEof)
+        ));
+        // TDAT that isn't "DDMM" digits is ignored (here 4 bytes of UTF-8).
+        let read = Id3v2::read(&tag(
+            b"TYER\x00\x00\x00\x05\x00\x00\x002018TDAT\x00\x00\x00\x04\x00\x00\x001\xE92",
+        ))
+        .unwrap();
+        assert_eq!(read.date(&Key::Release).map(|d| d.year), Some(2018));
+        assert_eq!(read.date(&Key::Release).and_then(|d| d.day), None);
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright Jeron Lau 2017 - 2018.
+// Dual-licensed under either the MIT License or the Boost Software License, Version 1.0.
+// (See accompanying file LICENSE_1_0.txt or copy at https://www.boost.org/LICENSE_1_0.txt)
+
+mod id3v1;
+mod id3v2;
+mod vorbis;
+
+pub use self::id3v1::Id3v1;
+pub use self::id3v2::Id3v2;
+pub use self::vorbis::VorbisComment;
+
+use Date;
+use Key;
+use Value;
+
+/// Convert tag text into a value of the type expected for `key`.
+fn typed(key: &Key, text: &str) -> Value {
+    match *key {
+        Key::Disc | Key::DiscCount | Key::TrackNumber | Key::TrackCount => {
+            match text.trim().parse() {
+                Ok(n) => Value::Integer(n),
+                Err(_) => Value::Text(text.to_string()),
+            }
+        }
+        Key::Release => match Date::parse(text) {
+            Some(date) => Value::Date(date),
+            None => Value::Text(text.to_string()),
+        },
+        _ => Value::Text(text.to_string()),
+    }
+}
+
+/// Split a "number/count" pair, like "3/12".
+fn split_pair(text: &str) -> (&str, Option<&str>) {
+    let mut parts = text.splitn(2, '/');
+    let number = parts.next().unwrap_or("");
+    (number, parts.next().filter(|c| !c.trim().is_empty()))
+}
+
+/// Decode ISO-8859-1 text.
+fn latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| b as char).collect()
+}
+
+/// Encode ISO-8859-1 text, replacing characters that don't fit with `?`.
+fn to_latin1(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| if (c as u32) < 256 { c as u8 } else { b'?' })
+        .collect()
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encode bytes as base64 (with padding).
+fn base64_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Decode base64 (padding optional, whitespace ignored).
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    let (mut n, mut bits) = (0u32, 0);
+    for c in text.bytes() {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            b' ' | b'\n' | b'\r' | b'\t' => continue,
+            _ => return None,
+        };
+        n = n << 6 | v as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((n >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64() {
+        for text in &["", "f", "fo", "foo", "foob", "fooba", "foobar"] {
+            let encoded = base64_encode(text.as_bytes());
+            assert_eq!(base64_decode(&encoded).unwrap(), text.as_bytes());
+        }
+        assert_eq!(base64_encode(b"foob"), "Zm9vYg==");
+    }
+}
@@ -0,0 +1,198 @@
+// Copyright Jeron Lau 2017 - 2018.
+// Dual-licensed under either the MIT License or the Boost Software License, Version 1.0.
+// (See accompanying file LICENSE_1_0.txt or copy at https://www.boost.org/LICENSE_1_0.txt)
+
+use super::{base64_decode, base64_encode, split_pair, typed};
+use Error;
+use Key;
+use Metadata;
+use Picture;
+use PictureKind;
+use Result;
+
+/// Field names other taggers use for well-known keys.
+const ALIASES: [(&str, Key); 5] = [
+    ("TOTALTRACKS", Key::TrackCount),
+    ("TOTALDISCS", Key::DiscCount),
+    ("ENCODED-BY", Key::EncodedBy),
+    ("YEAR", Key::Release),
+    ("DESCRIPTION", Key::Comment),
+];
+
+/// Name of the field for embedded pictures.
+const PICTURE: &str = "METADATA_BLOCK_PICTURE";
+
+/// A cursor for reading fields from bytes.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn u32(&mut self, big_endian: bool) -> Result<u32> {
+        let b = self.bytes(4)?;
+        let b = [b[0], b[1], b[2], b[3]];
+        Ok(if big_endian {
+            u32::from_be_bytes(b)
+        } else {
+            u32::from_le_bytes(b)
+        })
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.0.len() {
+            return Err(Error::UnexpectedEof);
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+}
+
+/// Read a FLAC picture block.
+fn read_picture(data: &[u8]) -> Result<Picture> {
+    let mut r = Reader(data);
+    let kind = r.u32(true)?;
+    let len = r.u32(true)? as usize;
+    let mime = String::from_utf8_lossy(r.bytes(len)?).into_owned();
+    let len = r.u32(true)? as usize;
+    let description = String::from_utf8_lossy(r.bytes(len)?).into_owned();
+    r.bytes(16)?; // Width, height, depth and number of colors.
+    let len = r.u32(true)? as usize;
+    Ok(Picture {
+        kind: PictureKind::from_u8(kind.min(255) as u8),
+        mime,
+        description,
+        data: r.bytes(len)?.to_vec(),
+    })
+}
+
+/// Write a FLAC picture block.  The dimensions are left as 0 (unknown).
+fn write_picture(picture: &Picture) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&(picture.kind as u32).to_be_bytes());
+    for text in &[&picture.mime, &picture.description] {
+        out.extend_from_slice(&(text.len() as u32).to_be_bytes());
+        out.extend_from_slice(text.as_bytes());
+    }
+    out.extend_from_slice(&[0; 16]);
+    out.extend_from_slice(&(picture.data.len() as u32).to_be_bytes());
+    out.extend_from_slice(&picture.data);
+    out
+}
+
+/// Vorbis comments: the tags used by Ogg Vorbis, Opus and FLAC.
+pub struct VorbisComment;
+
+impl VorbisComment {
+    /// Read a Vorbis comment block (without the Vorbis packet header or
+    /// framing bit).
+    pub fn read(data: &[u8]) -> Result<Metadata> {
+        let mut r = Reader(data);
+        let len = r.u32(false)? as usize;
+        r.bytes(len)?; // Vendor
+        let count = r.u32(false)?;
+
+        let mut metadata = Metadata::new();
+        for _ in 0..count {
+            let len = r.u32(false)? as usize;
+            let comment = String::from_utf8_lossy(r.bytes(len)?).into_owned();
+            let mut parts = comment.splitn(2, '=');
+            let (name, value) = match (parts.next(), parts.next()) {
+                (Some(name), Some(value)) => (name.to_uppercase(), value),
+                _ => continue,
+            };
+
+            if name == PICTURE {
+                let data = base64_decode(value)
+                    .ok_or_else(|| Error::Corrupt("invalid base64 picture".to_string()))?;
+                metadata.add(Key::Picture, read_picture(&data)?);
+                continue;
+            }
+            let key = match ALIASES.iter().find(|a| a.0 == name) {
+                Some((_, key)) => key.clone(),
+                None => Key::new(&name),
+            };
+            let count = match key {
+                Key::TrackNumber => Some(Key::TrackCount),
+                Key::Disc => Some(Key::DiscCount),
+                _ => None,
+            };
+            match count {
+                // Some taggers write "3/12" as the track number.
+                Some(count) => {
+                    let (n, c) = split_pair(value);
+                    metadata.add(key.clone(), typed(&key, n));
+                    if let Some(c) = c {
+                        metadata.add(count.clone(), typed(&count, c));
+                    }
+                }
+                None => metadata.add(key.clone(), typed(&key, value)),
+            }
+        }
+
+        Ok(metadata)
+    }
+
+    /// Write a Vorbis comment block (without the Vorbis packet header or
+    /// framing bit).  `vendor` names the encoding software.
+    pub fn write(metadata: &Metadata, vendor: &str) -> Vec<u8> {
+        let comments: Vec<String> = metadata
+            .iter()
+            .map(|(key, value)| match value.as_picture() {
+                Some(picture) => {
+                    format!("{}={}", PICTURE, base64_encode(&write_picture(picture)))
+                }
+                None => format!("{}={}", key.name(), value),
+            })
+            .collect();
+        let mut out = Vec::new();
+        out.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+        out.extend_from_slice(vendor.as_bytes());
+        out.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+        for comment in comments {
+            out.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+            out.extend_from_slice(comment.as_bytes());
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Date;
+
+    #[test]
+    fn round_trip() {
+        let mut metadata = Metadata::new();
+        metadata.add(Key::Title, "Ünïcode ♫");
+        metadata.add(Key::Artist, "One");
+        metadata.add(Key::Artist, "Two");
+        metadata.add(Key::TrackNumber, 3i64);
+        metadata.add(Key::Release, Date::new(2018, 5, 6));
+        metadata.add(
+            Key::Picture,
+            Picture {
+                kind: PictureKind::FrontCover,
+                mime: "image/jpeg".to_string(),
+                description: String::new(),
+                data: vec![0xFF, 0xD8, 0xFF, 0xE0],
+            },
+        );
+        let read = VorbisComment::read(&VorbisComment::write(&metadata, "afi")).unwrap();
+        assert_eq!(read, metadata);
+    }
+
+    #[test]
+    fn aliases() {
+        let mut data = vec![3, 0, 0, 0];
+        data.extend_from_slice(b"abc");
+        data.extend_from_slice(&[2, 0, 0, 0]);
+        for comment in &["tracknumber=4/9", "Year=1987"] {
+            data.extend_from_slice(&[comment.len() as u8, 0, 0, 0]);
+            data.extend_from_slice(comment.as_bytes());
+        }
+        let read = VorbisComment::read(&data).unwrap();
+        assert_eq!(read.integer(&Key::TrackNumber), Some(4));
+        assert_eq!(read.integer(&Key::TrackCount), Some(9));
+        assert_eq!(read.date(&Key::Release), Some(Date::year(1987)));
+    }
+}
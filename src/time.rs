@@ -0,0 +1,210 @@
+// Copyright Jeron Lau 2017 - 2018.
+// Dual-licensed under either the MIT License or the Boost Software License, Version 1.0.
+// (See accompanying file LICENSE_1_0.txt or copy at https://www.boost.org/LICENSE_1_0.txt)
+
+/// Greatest common divisor, for reducing fractions.
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        let t = a % b;
+        a = b;
+        b = t;
+    }
+    a
+}
+
+/// Divide, rounding to the nearest integer (halves away from zero).
+fn div_round(n: i128, d: i128) -> i128 {
+    let (n, d) = if d < 0 { (-n, -d) } else { (n, d) };
+    if n >= 0 {
+        (n + d / 2) / d
+    } else {
+        (n - d / 2) / d
+    }
+}
+
+/// Divide, rounding towards negative infinity.
+fn div_floor(n: i128, d: i128) -> i128 {
+    let q = n / d;
+    if (n % d != 0) && ((n < 0) != (d < 0)) {
+        q - 1
+    } else {
+        q
+    }
+}
+
+/// A rational timebase: `num / den` ticks per second.  For a frame rate the
+/// ticks are frames, so 29.97fps is `Timebase::new(30000, 1001)`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Timebase {
+    num: u32,
+    den: u32,
+}
+
+impl Timebase {
+    /// 24000/1001 (23.976) ticks per second.
+    pub const FPS_23_976: Timebase = Timebase {
+        num: 24000,
+        den: 1001,
+    };
+    /// 24 ticks per second.
+    pub const FPS_24: Timebase = Timebase { num: 24, den: 1 };
+    /// 25 ticks per second.
+    pub const FPS_25: Timebase = Timebase { num: 25, den: 1 };
+    /// 30000/1001 (29.97) ticks per second.
+    pub const FPS_29_97: Timebase = Timebase {
+        num: 30000,
+        den: 1001,
+    };
+    /// 30 ticks per second.
+    pub const FPS_30: Timebase = Timebase { num: 30, den: 1 };
+    /// 50 ticks per second.
+    pub const FPS_50: Timebase = Timebase { num: 50, den: 1 };
+    /// 60000/1001 (59.94) ticks per second.
+    pub const FPS_59_94: Timebase = Timebase {
+        num: 60000,
+        den: 1001,
+    };
+    /// 60 ticks per second.
+    pub const FPS_60: Timebase = Timebase { num: 60, den: 1 };
+    /// 1000 ticks per second (milliseconds).
+    pub const MILLIS: Timebase = Timebase { num: 1000, den: 1 };
+
+    /// Create a new timebase of `num / den` ticks per second.  The fraction
+    /// is reduced.  Panics if either `num` or `den` is zero.
+    pub fn new(num: u32, den: u32) -> Timebase {
+        assert!(num != 0 && den != 0, "Timebase can't have a zero term");
+        let d = gcd(num as u64, den as u64) as u32;
+        Timebase {
+            num: num / d,
+            den: den / d,
+        }
+    }
+
+    /// Create a timebase of a whole number of ticks per second.
+    pub fn fps(fps: u32) -> Timebase {
+        Timebase::new(fps, 1)
+    }
+
+    /// Get the numerator (ticks).
+    pub fn num(self) -> u32 {
+        self.num
+    }
+
+    /// Get the denominator (seconds).
+    pub fn den(self) -> u32 {
+        self.den
+    }
+
+    /// Get the number of ticks per second as a float.
+    pub fn to_f64(self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+
+    /// Convert a tick count in this timebase to seconds.
+    pub fn to_seconds(self, ticks: i64) -> f64 {
+        // Split into whole seconds and a remainder so that huge tick counts
+        // don't lose precision to the float multiplication.
+        let total = ticks as i128 * self.den as i128;
+        let whole = div_floor(total, self.num as i128);
+        let rem = total - whole * self.num as i128;
+        whole as f64 + (rem as f64 / self.num as f64)
+    }
+
+    /// Convert seconds to the nearest tick count in this timebase.
+    pub fn from_seconds(self, seconds: f64) -> i64 {
+        (seconds * self.num as f64 / self.den as f64).round() as i64
+    }
+
+    /// Convert a tick count in this timebase to the nearest tick count in
+    /// another timebase, using exact integer math.
+    pub fn rescale(self, ticks: i64, to: Timebase) -> i64 {
+        let n = ticks as i128 * to.num as i128 * self.den as i128;
+        let d = to.den as i128 * self.num as i128;
+        div_round(n, d) as i64
+    }
+}
+
+impl Default for Timebase {
+    fn default() -> Timebase {
+        Timebase::FPS_24
+    }
+}
+
+/// A point in time, counted in ticks of a timebase.  `==` compares both
+/// `ticks` and `timebase`, so the same instant in two timebases isn't equal;
+/// `rescale` one of them first to compare instants.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Timestamp {
+    /// Number of ticks since the start of the stream.
+    pub ticks: i64,
+    /// The timebase that `ticks` is counted in.
+    pub timebase: Timebase,
+}
+
+impl Timestamp {
+    /// Create a new timestamp.
+    pub fn new(ticks: i64, timebase: Timebase) -> Timestamp {
+        Timestamp { ticks, timebase }
+    }
+
+    /// Create the timestamp nearest to `seconds` in `timebase`.
+    pub fn from_seconds(seconds: f64, timebase: Timebase) -> Timestamp {
+        Timestamp::new(timebase.from_seconds(seconds), timebase)
+    }
+
+    /// Convert the timestamp to seconds.
+    pub fn to_seconds(self) -> f64 {
+        self.timebase.to_seconds(self.ticks)
+    }
+
+    /// Convert the timestamp to the nearest tick in another timebase.
+    pub fn rescale(self, timebase: Timebase) -> Timestamp {
+        Timestamp::new(self.timebase.rescale(self.ticks, timebase), timebase)
+    }
+
+    /// Convert the timestamp to the index of the frame that is showing at
+    /// this time, for a video with frame rate `timebase`.
+    pub fn to_index(self, timebase: Timebase) -> u32 {
+        let n = self.ticks as i128 * timebase.num as i128 * self.timebase.den as i128;
+        let d = timebase.den as i128 * self.timebase.num as i128;
+        div_floor(n, d).max(0).min(u32::MAX as i128) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reduce() {
+        assert_eq!(Timebase::new(48, 2), Timebase::FPS_24);
+        assert_eq!(Timebase::new(60000, 2002), Timebase::FPS_29_97);
+    }
+
+    #[test]
+    fn no_drift() {
+        // One hour of 29.97fps video is exactly 107892 frames (+ 0.108).
+        let frames = 107_892;
+        let ts = Timestamp::new(frames, Timebase::FPS_29_97);
+        let secs = ts.to_seconds();
+        assert!((secs - 3599.9964).abs() < 1e-9);
+        // 90kHz clock round trip is exact.
+        let clock = Timebase::fps(90_000);
+        let pts = ts.rescale(clock);
+        assert_eq!(pts.ticks, frames * 3003);
+        assert_eq!(pts.rescale(Timebase::FPS_29_97).ticks, frames);
+        assert_eq!(pts.to_index(Timebase::FPS_29_97), frames as u32);
+    }
+
+    #[test]
+    fn rescale_rounding() {
+        // 1 frame at 25fps is 0.96 frames at 24fps.
+        assert_eq!(Timebase::FPS_25.rescale(1, Timebase::FPS_24), 1);
+        assert_eq!(Timebase::FPS_25.rescale(-1, Timebase::FPS_24), -1);
+        assert_eq!(
+            Timestamp::new(1, Timebase::FPS_25).to_index(Timebase::FPS_24),
+            0
+        );
+        assert_eq!(Timebase::FPS_24.to_seconds(-12), -0.5);
+    }
+}
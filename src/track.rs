@@ -0,0 +1,180 @@
+// Copyright Jeron Lau 2017 - 2018.
+// Dual-licensed under either the MIT License or the Boost Software License, Version 1.0.
+// (See accompanying file LICENSE_1_0.txt or copy at https://www.boost.org/LICENSE_1_0.txt)
+
+use Audio;
+use AudioInfo;
+use Result;
+use StreamInfo;
+use Subtitles;
+use Video;
+use VideoInfo;
+
+/// The kind of data in a track.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum TrackKind {
+    /// Video (`Video`).
+    Video,
+    /// Audio (`Audio`).
+    Audio,
+    /// Subtitles or captions (`Subtitles`).
+    Subtitle,
+}
+
+/// What's known about a track before decoding it.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum TrackInfo {
+    /// A video track.
+    Video(VideoInfo),
+    /// An audio track.
+    Audio(AudioInfo),
+    /// A subtitle track.
+    Subtitle,
+}
+
+/// One of the streams in a file, like a video, an audio language or a
+/// commentary, or subtitles.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Track {
+    /// The track's id, unique in the file.
+    pub id: u32,
+    /// The language, as an IETF BCP 47 or ISO 639-2 code ("en", "eng").
+    pub language: Option<String>,
+    /// The track's name, like "Director's Commentary".
+    pub name: Option<String>,
+    /// Whether the track is played if none are chosen.
+    pub default: bool,
+    /// The kind of track, and what's known about it.
+    pub info: TrackInfo,
+}
+
+impl Track {
+    /// Create a track with no language or name.
+    pub fn new(id: u32, info: TrackInfo) -> Track {
+        Track {
+            id,
+            language: None,
+            name: None,
+            default: false,
+            info,
+        }
+    }
+
+    /// Get the kind of track.
+    pub fn kind(&self) -> TrackKind {
+        match self.info {
+            TrackInfo::Video(_) => TrackKind::Video,
+            TrackInfo::Audio(_) => TrackKind::Audio,
+            TrackInfo::Subtitle => TrackKind::Subtitle,
+        }
+    }
+}
+
+/// Decoded data for each track, by id (see `Decoder::run_tracks` and
+/// `EncoderTracks`).
+#[derive(Default)]
+pub struct Tracks {
+    /// Video tracks.
+    pub video: Vec<(u32, Video)>,
+    /// Audio tracks.
+    pub audio: Vec<(u32, Audio)>,
+    /// Subtitle tracks.
+    pub subtitles: Vec<(u32, Subtitles)>,
+}
+
+impl Tracks {
+    /// Create an empty set of tracks.
+    pub fn new() -> Tracks {
+        Tracks::default()
+    }
+
+    /// Get a video track.
+    pub fn video(&self, id: u32) -> Option<&Video> {
+        self.video.iter().find(|t| t.0 == id).map(|t| &t.1)
+    }
+
+    /// Get a video track to modify.
+    pub fn video_mut(&mut self, id: u32) -> Option<&mut Video> {
+        self.video.iter_mut().find(|t| t.0 == id).map(|t| &mut t.1)
+    }
+
+    /// Get an audio track.
+    pub fn audio(&self, id: u32) -> Option<&Audio> {
+        self.audio.iter().find(|t| t.0 == id).map(|t| &t.1)
+    }
+
+    /// Get an audio track to modify.
+    pub fn audio_mut(&mut self, id: u32) -> Option<&mut Audio> {
+        self.audio.iter_mut().find(|t| t.0 == id).map(|t| &mut t.1)
+    }
+
+    /// Get a subtitle track.
+    pub fn subtitles(&self, id: u32) -> Option<&Subtitles> {
+        self.subtitles.iter().find(|t| t.0 == id).map(|t| &t.1)
+    }
+
+    /// Get a subtitle track to modify.
+    pub fn subtitles_mut(&mut self, id: u32) -> Option<&mut Subtitles> {
+        self.subtitles
+            .iter_mut()
+            .find(|t| t.0 == id)
+            .map(|t| &mut t.1)
+    }
+
+    /// Decode with a single-track decoder's `run`, keeping its video and
+    /// audio as the first video and audio tracks in `info` (or tracks 0 and
+    /// 1 if there aren't any).
+    pub(crate) fn run_single<F>(&mut self, info: &StreamInfo, run: F) -> Result<bool>
+    where
+        F: FnOnce(&mut Option<Audio>, &mut Option<Video>) -> Result<bool>,
+    {
+        let id = |kind, or| {
+            info.tracks
+                .iter()
+                .find(|t| t.kind() == kind)
+                .map_or(or, |t| t.id)
+        };
+        let (video_id, audio_id) = (id(TrackKind::Video, 0), id(TrackKind::Audio, 1));
+        let video_at = self.video.iter().position(|t| t.0 == video_id);
+        let audio_at = self.audio.iter().position(|t| t.0 == audio_id);
+        let mut video = video_at.map(|i| self.video.remove(i).1);
+        let mut audio = audio_at.map(|i| self.audio.remove(i).1);
+        let more = run(&mut audio, &mut video);
+        if let Some(video) = video {
+            let i = video_at.unwrap_or(self.video.len());
+            self.video.insert(i, (video_id, video));
+        }
+        if let Some(audio) = audio {
+            let i = audio_at.unwrap_or(self.audio.len());
+            self.audio.insert(i, (audio_id, audio));
+        }
+        more
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use AudioChannels;
+    use ColorChannels;
+
+    #[test]
+    fn run_single() {
+        let mut info = StreamInfo::default();
+        info.tracks.push(Track::new(7, TrackInfo::Subtitle));
+        let mut tracks = Tracks::new();
+        tracks
+            .audio
+            .push((2, Audio::new(AudioChannels::Mono, 8000, 0)));
+        let more = tracks.run_single(&info, |audio, video| {
+            assert!(audio.is_none() && video.is_none());
+            *video = Some(Video::new(ColorChannels::Srgb, (2, 2), 1));
+            *audio = Some(Audio::new(AudioChannels::Stereo, 8000, 0));
+            Ok(true)
+        });
+        assert!(more.unwrap());
+        assert!(tracks.video(0).is_some());
+        assert_eq!(tracks.audio(1).unwrap().n_channels(), 2);
+        assert_eq!(tracks.audio(2).unwrap().n_channels(), 1);
+    }
+}
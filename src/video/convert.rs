@@ -0,0 +1,762 @@
+// Copyright Jeron Lau 2017 - 2018.
+// Dual-licensed under either the MIT License or the Boost Software License, Version 1.0.
+// (See accompanying file LICENSE_1_0.txt or copy at https://www.boost.org/LICENSE_1_0.txt)
+
+//! Fast paths for converting whole frames of 8-bit pixels, which give
+//! exactly the same results as `ColorChannels::from`.
+//!
+//! * sRGBA <-> sBGRA swaps red and blue with SSE2 on x86_64 (and a scalar
+//!   loop elsewhere).
+//! * Other RGB conversions (3-channel swizzles, and to, from or between
+//!   linear RGB) are scalar loops over look up tables.
+//! * RGB <-> YCbCr runs each format's formulas on 8 pixels at a time with
+//!   `Simd` (AVX2 when the CPU has it or SSE2 on x86_64, NEON on aarch64 and
+//!   plain arrays elsewhere), in the same order of float operations as
+//!   `ColorChannels::from`.  Linear RGB goes through a look up table first.
+//!
+//! On one core of a 2 GHz Xeon, with a 3840×2160 frame (see
+//! `examples/convert.rs`), swapping red and blue takes about 10ms, other RGB
+//! conversions 15 to 20ms and YCbCr 10 to 25ms with AVX2, under the 33ms of
+//! a frame at 30 frames per second.  With only SSE2, YCbCr takes 25 to 50ms.
+
+use std::sync::OnceLock;
+
+use super::blend::STOLINLUT;
+#[cfg(target_arch = "aarch64")]
+use super::simd::Neon;
+#[cfg(any(test, not(any(target_arch = "x86_64", target_arch = "aarch64"))))]
+use super::simd::Scalar;
+use super::simd::Simd;
+#[cfg(target_arch = "x86_64")]
+use super::simd::{Avx2, Sse2};
+use super::{float_to_u8, linear_to_s, u8_to_float};
+use ColorChannels;
+use ColorChannels::*;
+use Depth;
+
+/// u8 look up tables for sRGB <-> linear.
+struct Luts {
+    s_to_linear: [u8; 256],
+    linear_to_s: [u8; 256],
+    /// Linear to sRGB and back, like `ColorChannels::from` does.
+    linear_to_linear: [u8; 256],
+    same: [u8; 256],
+}
+
+fn luts() -> &'static Luts {
+    static LUTS: OnceLock<Luts> = OnceLock::new();
+    LUTS.get_or_init(|| {
+        let mut luts = Luts {
+            s_to_linear: [0; 256],
+            linear_to_s: [0; 256],
+            linear_to_linear: [0; 256],
+            same: [0; 256],
+        };
+        for (i, linear) in STOLINLUT.iter().enumerate() {
+            luts.s_to_linear[i] = float_to_u8(*linear);
+            luts.linear_to_s[i] = float_to_u8(linear_to_s(u8_to_float(i as u8)));
+            luts.same[i] = i as u8;
+        }
+        for i in 0..256 {
+            luts.linear_to_linear[i] = luts.s_to_linear[luts.linear_to_s[i] as usize];
+        }
+        luts
+    })
+}
+
+/// Get the look up table from sRGB or linear to sRGB or linear.
+fn lut(from_linear: bool, to_linear: bool) -> &'static [u8; 256] {
+    let luts = luts();
+    match (from_linear, to_linear) {
+        (false, true) => &luts.s_to_linear,
+        (true, false) => &luts.linear_to_s,
+        (true, true) => &luts.linear_to_linear,
+        (false, false) => &luts.same,
+    }
+}
+
+/// S space to Linear space for u8s, from a look up table.
+pub(super) fn s_to_linear_u8(s: u8) -> u8 {
+    luts().s_to_linear[s as usize]
+}
+
+/// Linear space to S space for u8s, from a look up table.
+pub(super) fn linear_to_s_u8(l: u8) -> u8 {
+    luts().linear_to_s[l as usize]
+}
+
+/// Where the red, green, blue and alpha channels of an RGB format are, and
+/// whether it's linear.
+#[derive(Copy, Clone)]
+struct Rgb {
+    linear: bool,
+    rgb: [usize; 3],
+    alpha: Option<usize>,
+}
+
+impl Rgb {
+    fn new(format: ColorChannels) -> Option<Rgb> {
+        let (linear, rgb, alpha) = match format {
+            Srgb => (false, [0, 1, 2], None),
+            Srgba => (false, [0, 1, 2], Some(3)),
+            Sbgr => (false, [2, 1, 0], None),
+            Sbgra => (false, [2, 1, 0], Some(3)),
+            Lrgb => (true, [0, 1, 2], None),
+            Lrgba => (true, [0, 1, 2], Some(3)),
+            Lbgr => (true, [2, 1, 0], None),
+            Lbgra => (true, [2, 1, 0], Some(3)),
+            _ => return None,
+        };
+        Some(Rgb { linear, rgb, alpha })
+    }
+
+    /// Get a pixel as RGB (through `lut`) and alpha.
+    #[inline(always)]
+    fn get(self, p: &[u8], lut: &[u8; 256]) -> [u8; 4] {
+        let a = self.alpha.map_or(255, |a| p[a]);
+        [
+            lut[p[self.rgb[0]] as usize],
+            lut[p[self.rgb[1]] as usize],
+            lut[p[self.rgb[2]] as usize],
+            a,
+        ]
+    }
+
+    /// Set a pixel from RGB (in this format's space) and alpha.
+    #[inline(always)]
+    fn set(self, p: &mut [u8], rgba: [u8; 4]) {
+        p[self.rgb[0]] = rgba[0];
+        p[self.rgb[1]] = rgba[1];
+        p[self.rgb[2]] = rgba[2];
+        if let Some(a) = self.alpha {
+            p[a] = rgba[3];
+        }
+    }
+}
+
+/// Swap the red and blue channels of 4-byte pixels.
+fn swap_rb(src: &[u8], dst: &mut [u8]) {
+    let done = swap_rb_simd(src, dst);
+    for (s, d) in src[done..]
+        .chunks_exact(4)
+        .zip(dst[done..].chunks_exact_mut(4))
+    {
+        d.copy_from_slice(&[s[2], s[1], s[0], s[3]]);
+    }
+}
+
+/// Swap the red and blue channels of 4 pixels at a time, returning the
+/// number of bytes done.
+#[cfg(target_arch = "x86_64")]
+fn swap_rb_simd(src: &[u8], dst: &mut [u8]) -> usize {
+    use std::arch::x86_64::*;
+
+    let done = src.len() / 16 * 16;
+    // SSE2 is always available on x86_64.
+    unsafe {
+        let ga = _mm_set1_epi32(0xFF00_FF00u32 as i32);
+        let low = _mm_set1_epi32(0xFF);
+        for (s, d) in src[..done].chunks_exact(16).zip(dst.chunks_exact_mut(16)) {
+            let v = _mm_loadu_si128(s.as_ptr() as *const __m128i);
+            let r = _mm_slli_epi32(_mm_and_si128(v, low), 16);
+            let b = _mm_and_si128(_mm_srli_epi32(v, 16), low);
+            let v = _mm_or_si128(_mm_and_si128(v, ga), _mm_or_si128(r, b));
+            _mm_storeu_si128(d.as_mut_ptr() as *mut __m128i, v);
+        }
+    }
+    done
+}
+
+/// Swap the red and blue channels of 4 pixels at a time, returning the
+/// number of bytes done.
+#[cfg(not(target_arch = "x86_64"))]
+fn swap_rb_simd(_src: &[u8], _dst: &mut [u8]) -> usize {
+    0
+}
+
+/// Convert 8-bit pixels from one format to another with a fast path, if
+/// there is one (between RGB formats, and from and to YCbCr).  Returns false
+/// if there isn't.
+pub(crate) fn convert_u8(
+    from: ColorChannels,
+    to: ColorChannels,
+    src: &[u8],
+    dst: &mut [u8],
+) -> bool {
+    let (n_from, n_to) = (from.n_channels(), to.n_channels());
+    // Linear to linear goes through sRGB (which loses precision), so only
+    // sRGB can be swapped as is.
+    if n_from == 4 && n_to == 4 {
+        if let (Some(f), Some(t)) = (Rgb::new(from), Rgb::new(to)) {
+            if !f.linear && !t.linear {
+                swap_rb(src, dst);
+                return true;
+            }
+        }
+    }
+    match (Rgb::new(from), Rgb::new(to)) {
+        (Some(f), Some(t)) => match (n_from, n_to) {
+            (3, 3) => rgb::<3, 3>(f, t, src, dst),
+            (3, _) => rgb::<3, 4>(f, t, src, dst),
+            (_, 3) => rgb::<4, 3>(f, t, src, dst),
+            _ => rgb::<4, 4>(f, t, src, dst),
+        },
+        (Some(f), None) => match n_from {
+            3 => return to_ycbcr::<3>(f, to, src, dst),
+            _ => return to_ycbcr::<4>(f, to, src, dst),
+        },
+        (None, Some(t)) => match n_to {
+            3 => return from_ycbcr::<3>(from, t, src, dst),
+            _ => return from_ycbcr::<4>(from, t, src, dst),
+        },
+        (None, None) => return false,
+    }
+    true
+}
+
+/// Convert between RGB formats (with `NS` and `ND` channels each).
+fn rgb<const NS: usize, const ND: usize>(f: Rgb, t: Rgb, src: &[u8], dst: &mut [u8]) {
+    let lut = lut(f.linear, t.linear);
+    for (s, d) in src.chunks_exact(NS).zip(dst.chunks_exact_mut(ND)) {
+        t.set(d, f.get(s, lut));
+    }
+}
+
+/// Convert from RGB (with `NS` channels) to YCbCr, with the same formulas
+/// as `ColorChannels::from`.  Returns false if `to` isn't YCbCr.
+fn to_ycbcr<const NS: usize>(f: Rgb, to: ColorChannels, src: &[u8], dst: &mut [u8]) -> bool {
+    // Match on the format outside of the loop, so each loop only has one
+    // format's formula in it.
+    match to {
+        YCbCr => rgb_to::<NS, _>(f, to, Jpeg, src, dst),
+        YuvItur => rgb_to::<NS, _>(f, to, Itur, src, dst),
+        YuvNtsc => rgb_to::<NS, _>(f, to, Ntsc, src, dst),
+        YCbCr709 | YCbCr709Limited | YCbCr2020 | YCbCr2020Limited => {
+            rgb_to::<NS, _>(f, to, Matrix::new(to), src, dst)
+        }
+        _ => return false,
+    }
+    true
+}
+
+/// Convert from YCbCr to RGB (with `ND` channels), with the same formulas
+/// as `ColorChannels::from`.  Returns false if `from` isn't YCbCr.
+fn from_ycbcr<const ND: usize>(from: ColorChannels, t: Rgb, src: &[u8], dst: &mut [u8]) -> bool {
+    match from {
+        YCbCr => to_rgb::<ND, _>(from, t, Jpeg, src, dst),
+        YuvItur => to_rgb::<ND, _>(from, t, Itur, src, dst),
+        YuvNtsc => to_rgb::<ND, _>(from, t, Ntsc, src, dst),
+        YCbCr709 | YCbCr709Limited | YCbCr2020 | YCbCr2020Limited => {
+            to_rgb::<ND, _>(from, t, Matrix::new(from), src, dst)
+        }
+        _ => return false,
+    }
+    true
+}
+
+/// Convert RGB pixels (with `NS` channels) to the YCbCr format `to`, 8 at a
+/// time with `kernel` and the rest one at a time.
+fn rgb_to<const NS: usize, K: Kernel>(
+    f: Rgb,
+    to: ColorChannels,
+    kernel: K,
+    src: &[u8],
+    dst: &mut [u8],
+) {
+    let done = simd_rgb_to::<K, NS>(f, kernel, src, dst);
+    let lut = lut(f.linear, false);
+    let pixels = src[NS * done..].chunks_exact(NS);
+    for (s, d) in pixels.zip(dst[3 * done..].chunks_exact_mut(3)) {
+        d.copy_from_slice(&to.srgba_to(f.get(s, lut))[..3]);
+    }
+}
+
+/// Convert pixels in the YCbCr format `from` to RGB (with `ND` channels), 8
+/// at a time with `kernel` and the rest one at a time.
+fn to_rgb<const ND: usize, K: Kernel>(
+    from: ColorChannels,
+    t: Rgb,
+    kernel: K,
+    src: &[u8],
+    dst: &mut [u8],
+) {
+    let done = simd_to_rgb::<K, ND>(t, kernel, src, dst);
+    let lut = lut(false, t.linear);
+    let pixels = src[3 * done..].chunks_exact(3);
+    for (s, d) in pixels.zip(dst[ND * done..].chunks_exact_mut(ND)) {
+        let [r, g, b, a] = from.to_srgba([s[0], s[1], s[2], 255]);
+        t.set(d, [lut[r as usize], lut[g as usize], lut[b as usize], a]);
+    }
+}
+
+/// Run `lanes_rgb_to` with the best SIMD the CPU has, returning the number
+/// of pixels done.
+#[cfg(target_arch = "x86_64")]
+fn simd_rgb_to<K: Kernel, const NS: usize>(f: Rgb, kernel: K, src: &[u8], dst: &mut [u8]) -> usize {
+    if is_x86_feature_detected!("avx2") {
+        unsafe { avx2_rgb_to::<K, NS>(f, kernel, src, dst) }
+    } else {
+        unsafe { lanes_rgb_to::<Sse2, K, NS>(f, kernel, src, dst) }
+    }
+}
+
+/// `lanes_rgb_to` with AVX2, which the CPU must have.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn avx2_rgb_to<K: Kernel, const NS: usize>(
+    f: Rgb,
+    kernel: K,
+    src: &[u8],
+    dst: &mut [u8],
+) -> usize {
+    lanes_rgb_to::<Avx2, K, NS>(f, kernel, src, dst)
+}
+
+/// Run `lanes_rgb_to` with NEON, returning the number of pixels done.
+#[cfg(target_arch = "aarch64")]
+fn simd_rgb_to<K: Kernel, const NS: usize>(f: Rgb, kernel: K, src: &[u8], dst: &mut [u8]) -> usize {
+    unsafe { lanes_rgb_to::<Neon, K, NS>(f, kernel, src, dst) }
+}
+
+/// Run `lanes_rgb_to` without SIMD, returning the number of pixels done.
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+fn simd_rgb_to<K: Kernel, const NS: usize>(f: Rgb, kernel: K, src: &[u8], dst: &mut [u8]) -> usize {
+    unsafe { lanes_rgb_to::<Scalar, K, NS>(f, kernel, src, dst) }
+}
+
+/// Run `lanes_to_rgb` with the best SIMD the CPU has, returning the number
+/// of pixels done.
+#[cfg(target_arch = "x86_64")]
+fn simd_to_rgb<K: Kernel, const ND: usize>(t: Rgb, kernel: K, src: &[u8], dst: &mut [u8]) -> usize {
+    if is_x86_feature_detected!("avx2") {
+        unsafe { avx2_to_rgb::<K, ND>(t, kernel, src, dst) }
+    } else {
+        unsafe { lanes_to_rgb::<Sse2, K, ND>(t, kernel, src, dst) }
+    }
+}
+
+/// `lanes_to_rgb` with AVX2, which the CPU must have.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn avx2_to_rgb<K: Kernel, const ND: usize>(
+    t: Rgb,
+    kernel: K,
+    src: &[u8],
+    dst: &mut [u8],
+) -> usize {
+    lanes_to_rgb::<Avx2, K, ND>(t, kernel, src, dst)
+}
+
+/// Run `lanes_to_rgb` with NEON, returning the number of pixels done.
+#[cfg(target_arch = "aarch64")]
+fn simd_to_rgb<K: Kernel, const ND: usize>(t: Rgb, kernel: K, src: &[u8], dst: &mut [u8]) -> usize {
+    unsafe { lanes_to_rgb::<Neon, K, ND>(t, kernel, src, dst) }
+}
+
+/// Run `lanes_to_rgb` without SIMD, returning the number of pixels done.
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+fn simd_to_rgb<K: Kernel, const ND: usize>(t: Rgb, kernel: K, src: &[u8], dst: &mut [u8]) -> usize {
+    unsafe { lanes_to_rgb::<Scalar, K, ND>(t, kernel, src, dst) }
+}
+
+/// Convert RGB pixels (with `NS` channels) to YCbCr 8 at a time with
+/// `kernel`, returning the number of pixels done.  Linear RGB goes through a
+/// look up table first.
+#[inline(always)]
+unsafe fn lanes_rgb_to<S: Simd, K: Kernel, const NS: usize>(
+    f: Rgb,
+    kernel: K,
+    src: &[u8],
+    dst: &mut [u8],
+) -> usize {
+    let lut = lut(f.linear, false);
+    let mut srgb = [0; 32];
+    let blocks = src.chunks_exact(8 * NS).zip(dst.chunks_exact_mut(8 * 3));
+    let done = 8 * blocks.len();
+    for (s, d) in blocks {
+        let s = if f.linear {
+            for (c, s) in srgb.iter_mut().zip(s) {
+                *c = lut[*s as usize];
+            }
+            &srgb[..]
+        } else {
+            s
+        };
+        let [c0, c1, c2] = S::load::<NS>(s);
+        let rgb = if f.rgb[0] == 0 {
+            [c0, c1, c2]
+        } else {
+            [c2, c1, c0]
+        };
+        S::store::<3>(kernel.to_ycbcr::<S>(rgb), d);
+    }
+    done
+}
+
+/// Convert YCbCr pixels to RGB (with `ND` channels) 8 at a time with
+/// `kernel`, returning the number of pixels done.  Linear RGB goes through a
+/// look up table after.
+#[inline(always)]
+unsafe fn lanes_to_rgb<S: Simd, K: Kernel, const ND: usize>(
+    t: Rgb,
+    kernel: K,
+    src: &[u8],
+    dst: &mut [u8],
+) -> usize {
+    let lut = lut(false, t.linear);
+    let blocks = src.chunks_exact(8 * 3).zip(dst.chunks_exact_mut(8 * ND));
+    let done = 8 * blocks.len();
+    for (s, d) in blocks {
+        let [r, g, b] = kernel.to_rgb::<S>(S::load::<3>(s));
+        let c = if t.rgb[0] == 0 { [r, g, b] } else { [b, g, r] };
+        S::store::<ND>(c, d);
+        if t.linear {
+            for p in d.chunks_exact_mut(ND) {
+                for c in &mut p[..3] {
+                    *c = lut[*c as usize];
+                }
+            }
+        }
+    }
+    done
+}
+
+/// The formulas of a YCbCr format for 8 pixels at a time, with the same
+/// operations as `ColorChannels::from` does for each pixel.
+trait Kernel: Copy {
+    /// Convert sRGB to YCbCr.
+    unsafe fn to_ycbcr<S: Simd>(self, rgb: [S::I; 3]) -> [S::I; 3];
+    /// Convert YCbCr to sRGB.
+    unsafe fn to_rgb<S: Simd>(self, ycbcr: [S::I; 3]) -> [S::I; 3];
+}
+
+/// `round_u8` for 8 lanes.
+#[inline(always)]
+unsafe fn round<S: Simd>(a: S::F) -> S::I {
+    let a = S::mul_f(a, S::splat_f(255.0));
+    let a = S::min_f(S::max_f(a, S::splat_f(0.0)), S::splat_f(255.0));
+    let t = S::trunc_f(a);
+    // Subtracting -1 rounds up from a half or more.
+    let up = S::ge_f(S::sub_f(a, S::to_f(t)), S::splat_f(0.5));
+    S::sub(t, up)
+}
+
+/// `trunc_u8` for 8 lanes.
+#[inline(always)]
+unsafe fn trunc<S: Simd>(a: S::D) -> S::I {
+    S::trunc_d(S::min_d(S::max_d(a, S::splat_d(0.0)), S::splat_d(255.0)))
+}
+
+/// Clamp to a u8 for 8 lanes.
+#[inline(always)]
+unsafe fn clamp<S: Simd>(a: S::I) -> S::I {
+    S::min(S::max(a, S::splat(0)), S::splat(255))
+}
+
+/// `YCbCr` (BT.601 in f64, as in JPEG).
+#[derive(Copy, Clone)]
+struct Jpeg;
+
+impl Kernel for Jpeg {
+    #[inline(always)]
+    unsafe fn to_ycbcr<S: Simd>(self, rgb: [S::I; 3]) -> [S::I; 3] {
+        let (add, sub, mul, k) = (S::add_d, S::sub_d, S::mul_d, S::splat_d);
+        let [r, g, b] = rgb;
+        let (r, g, b) = (S::to_d(r), S::to_d(g), S::to_d(b));
+        let y = add(add(mul(k(0.299), r), mul(k(0.587), g)), mul(k(0.114), b));
+        let cb = sub(k(128.0), mul(k(0.168736), r));
+        let cb = add(sub(cb, mul(k(0.331264), g)), mul(k(0.5), b));
+        let cr = add(k(128.0), mul(k(0.5), r));
+        let cr = sub(sub(cr, mul(k(0.418688), g)), mul(k(0.081312), b));
+        [trunc::<S>(y), trunc::<S>(cb), trunc::<S>(cr)]
+    }
+
+    #[inline(always)]
+    unsafe fn to_rgb<S: Simd>(self, ycbcr: [S::I; 3]) -> [S::I; 3] {
+        let (add, sub, mul, k) = (S::add_d, S::sub_d, S::mul_d, S::splat_d);
+        let [y, cb, cr] = ycbcr;
+        let (y, cb, cr) = (S::to_d(y), S::to_d(cb), S::to_d(cr));
+        let (cb, cr) = (sub(cb, k(128.0)), sub(cr, k(128.0)));
+        let r = add(y, mul(k(1.402), cr));
+        let g = sub(sub(y, mul(k(0.344136), cb)), mul(k(0.714136), cr));
+        let b = add(y, mul(k(1.772), cb));
+        [trunc::<S>(r), trunc::<S>(g), trunc::<S>(b)]
+    }
+}
+
+/// `YuvItur` (BT.601 in f64, and integers back).
+#[derive(Copy, Clone)]
+struct Itur;
+
+impl Kernel for Itur {
+    #[inline(always)]
+    unsafe fn to_ycbcr<S: Simd>(self, rgb: [S::I; 3]) -> [S::I; 3] {
+        let (add, sub, mul, k) = (S::add_d, S::sub_d, S::mul_d, S::splat_d);
+        let [r, g, b] = rgb;
+        let (r, g, b) = (S::to_d(r), S::to_d(g), S::to_d(b));
+        let y = add(add(mul(k(0.299), r), mul(k(0.587), g)), mul(k(0.114), b));
+        let cb = sub(mul(k(-0.169), r), mul(k(0.331), g));
+        let cb = add(add(cb, mul(k(0.499), b)), k(128.0));
+        let cr = sub(mul(k(0.499), r), mul(k(0.418), g));
+        let cr = add(sub(cr, mul(k(0.0813), b)), k(128.0));
+        [trunc::<S>(y), trunc::<S>(cb), trunc::<S>(cr)]
+    }
+
+    #[inline(always)]
+    unsafe fn to_rgb<S: Simd>(self, ycbcr: [S::I; 3]) -> [S::I; 3] {
+        let (add, sub, shr, k) = (S::add, S::sub, S::shr, S::splat);
+        let [y, cb, cr] = ycbcr;
+        let (cb, cr) = (sub(cb, k(128)), sub(cr, k(128)));
+        let r = add(add(add(add(y, cr), shr(cr, 2)), shr(cr, 3)), shr(cr, 5));
+        let g_cb = add(add(shr(cb, 2), shr(cb, 4)), shr(cb, 5));
+        let g_cr = add(add(add(shr(cr, 1), shr(cr, 3)), shr(cr, 4)), shr(cr, 5));
+        let g = sub(sub(y, g_cb), g_cr);
+        let b = add(add(add(add(y, cb), shr(cb, 1)), shr(cb, 2)), shr(cb, 6));
+        [clamp::<S>(r), clamp::<S>(g), clamp::<S>(b)]
+    }
+}
+
+/// `YuvNtsc` (BT.601 limited range in integers).
+#[derive(Copy, Clone)]
+struct Ntsc;
+
+impl Kernel for Ntsc {
+    #[inline(always)]
+    unsafe fn to_ycbcr<S: Simd>(self, rgb: [S::I; 3]) -> [S::I; 3] {
+        // Always from 16 to 240, so there's nothing to clamp.
+        [
+            ntsc_row::<S>(rgb, [66, 129, 25], 16),
+            ntsc_row::<S>(rgb, [-38, -74, 112], 128),
+            ntsc_row::<S>(rgb, [112, -94, -18], 128),
+        ]
+    }
+
+    #[inline(always)]
+    unsafe fn to_rgb<S: Simd>(self, ycbcr: [S::I; 3]) -> [S::I; 3] {
+        let (add, sub, mul, shr, k) = (S::add, S::sub, S::mul, S::shr, S::splat);
+        let [y, cb, cr] = ycbcr;
+        let c = mul(k(298), sub(y, k(16)));
+        let (d, e) = (sub(cb, k(128)), sub(cr, k(128)));
+        let r = add(c, mul(k(409), e));
+        let g = add(add(c, mul(k(-100), d)), mul(k(-208), e));
+        let b = add(c, mul(k(516), d));
+        let r = clamp::<S>(shr(add(r, k(128)), 8));
+        let g = clamp::<S>(shr(add(g, k(128)), 8));
+        let b = clamp::<S>(shr(add(b, k(128)), 8));
+        [r, g, b]
+    }
+}
+
+/// One row of `YuvNtsc`'s matrix, with integer coefficients `k` and an
+/// offset.
+#[inline(always)]
+unsafe fn ntsc_row<S: Simd>(rgb: [S::I; 3], k: [i32; 3], offset: i32) -> S::I {
+    let (add, mul, shr) = (S::add, S::mul, S::shr);
+    let [r, g, b] = rgb;
+    let sum = mul(S::splat(k[0]), r);
+    let sum = add(add(sum, mul(S::splat(k[1]), g)), mul(S::splat(k[2]), b));
+    add(shr(add(sum, S::splat(128)), 8), S::splat(offset))
+}
+
+/// `YCbCr709`, `YCbCr2020` and their limited range formats (in f32, with
+/// `YCbCrMatrix::to_ycbcr` and `to_rgb`).
+#[derive(Copy, Clone)]
+struct Matrix {
+    kr: f32,
+    kb: f32,
+    levels: (f32, f32, f32, f32),
+}
+
+impl Matrix {
+    fn new(format: ColorChannels) -> Matrix {
+        let (matrix, range) = format.ycbcr_matrix().unwrap();
+        let (kr, kb) = matrix.kr_kb();
+        let levels = range.levels(Depth::U8);
+        Matrix { kr, kb, levels }
+    }
+}
+
+impl Kernel for Matrix {
+    #[inline(always)]
+    unsafe fn to_ycbcr<S: Simd>(self, rgb: [S::I; 3]) -> [S::I; 3] {
+        let (add, sub, mul, div, k) = (S::add_f, S::sub_f, S::mul_f, S::div_f, S::splat_f);
+        let (kr, kb) = (self.kr, self.kb);
+        let (offset, scale, center, c_scale) = self.levels;
+        let [r, g, b] = rgb;
+        let r = div(S::to_f(r), k(255.0));
+        let g = div(S::to_f(g), k(255.0));
+        let b = div(S::to_f(b), k(255.0));
+        let y = add(add(mul(k(kr), r), mul(k(1.0 - kr - kb), g)), mul(k(kb), b));
+        let cb = div(sub(b, y), k(2.0 * (1.0 - kb)));
+        let cr = div(sub(r, y), k(2.0 * (1.0 - kr)));
+        [
+            round::<S>(add(k(offset), mul(k(scale), y))),
+            round::<S>(add(k(center), mul(k(c_scale), cb))),
+            round::<S>(add(k(center), mul(k(c_scale), cr))),
+        ]
+    }
+
+    #[inline(always)]
+    unsafe fn to_rgb<S: Simd>(self, ycbcr: [S::I; 3]) -> [S::I; 3] {
+        let (add, sub, mul, div, k) = (S::add_f, S::sub_f, S::mul_f, S::div_f, S::splat_f);
+        let (kr, kb) = (self.kr, self.kb);
+        let (offset, scale, center, c_scale) = self.levels;
+        let [y, cb, cr] = ycbcr;
+        let y = div(sub(div(S::to_f(y), k(255.0)), k(offset)), k(scale));
+        let cb = div(sub(div(S::to_f(cb), k(255.0)), k(center)), k(c_scale));
+        let cr = div(sub(div(S::to_f(cr), k(255.0)), k(center)), k(c_scale));
+        let r = add(y, mul(k(2.0 * (1.0 - kr)), cr));
+        let b = add(y, mul(k(2.0 * (1.0 - kb)), cb));
+        let g = div(sub(sub(y, mul(k(kr), r)), mul(k(kb), b)), k(1.0 - kr - kb));
+        [round::<S>(r), round::<S>(g), round::<S>(b)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(from: ColorChannels, to: ColorChannels, src: &[u8]) -> Vec<u8> {
+        let len = src.len() / from.n_channels();
+        let mut dst = vec![0; len * to.n_channels()];
+        assert!(convert_u8(from, to, src, &mut dst));
+        dst
+    }
+
+    #[test]
+    fn luts_match() {
+        for i in 0..=255u8 {
+            let linear = super::super::s_to_linear(u8_to_float(i));
+            assert!((s_to_linear_u8(i) as i16 - float_to_u8(linear) as i16).abs() <= 1);
+        }
+    }
+
+    #[test]
+    fn rgb() {
+        let src: Vec<u8> = (0..40).collect();
+        let bgra = convert(Srgba, Sbgra, &src);
+        assert_eq!(&bgra[32..36], &[34, 33, 32, 35]);
+        assert_eq!(convert(Sbgra, Srgba, &bgra), src);
+        assert_eq!(convert(Srgba, Sbgr, &src[..8]), vec![2, 1, 0, 6, 5, 4]);
+        assert_eq!(
+            convert(Srgb, Lbgra, &[255, 128, 0]),
+            vec![0, s_to_linear_u8(128), 255, 255]
+        );
+    }
+
+    const RGB: [ColorChannels; 8] = [Srgb, Srgba, Sbgr, Sbgra, Lrgb, Lrgba, Lbgr, Lbgra];
+    const YCBCR: [ColorChannels; 7] = [
+        YCbCr,
+        YuvItur,
+        YuvNtsc,
+        YCbCr709,
+        YCbCr709Limited,
+        YCbCr2020,
+        YCbCr2020Limited,
+    ];
+
+    /// Check that the fast path gives exactly what `ColorChannels::from`
+    /// does for each pixel.
+    fn check(from: ColorChannels, to: ColorChannels, pixels: &[[u8; 4]]) {
+        let (n_from, n_to) = (from.n_channels(), to.n_channels());
+        let src: Vec<u8> = pixels.iter().flat_map(|p| p[..n_from].to_vec()).collect();
+        let fast = convert(from, to, &src);
+        for (s, d) in src.chunks(n_from).zip(fast.chunks(n_to)) {
+            let mut p = [0, 0, 0, 255];
+            p[..n_from].copy_from_slice(s);
+            assert_eq!(d, &to.from(from, p)[..n_to], "{:?} to {:?}", from, to);
+        }
+    }
+
+    /// Every value of each channel, not a whole number of 8 pixels long.
+    fn pixels() -> Vec<[u8; 4]> {
+        (0..0x1000 + 5u32)
+            .map(|i| {
+                [
+                    i as u8,
+                    (i >> 4) as u8,
+                    (i * 7 + (i >> 9)) as u8,
+                    (i * 3) as u8,
+                ]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matches_from() {
+        let pixels = pixels();
+        for &from in RGB.iter().chain(&YCBCR) {
+            for &to in RGB.iter().chain(&YCBCR) {
+                if from != to && (Rgb::new(from).is_some() || Rgb::new(to).is_some()) {
+                    check(from, to, &pixels);
+                }
+            }
+        }
+        // Grayscale and HSV have no fast path.
+        assert!(!convert_u8(Srgba, Sgrayscale, &[0; 4], &mut [0; 1]));
+        assert!(!convert_u8(Lhsva, YCbCr, &[0; 4], &mut [0; 3]));
+    }
+
+    /// Check `kernel` for `format` with `S`, to and from sRGB with `N`
+    /// channels.
+    fn check_lanes<S: Simd, K: Kernel, const N: usize>(format: ColorChannels, kernel: K) {
+        let rgb = if N == 3 { Srgb } else { Srgba };
+        let f = Rgb::new(rgb).unwrap();
+        let pixels = pixels();
+        let src: Vec<u8> = pixels.iter().flat_map(|p| p[..N].to_vec()).collect();
+        let mut dst = vec![0; pixels.len() * 3];
+        let done = unsafe { lanes_rgb_to::<S, K, N>(f, kernel, &src, &mut dst) };
+        assert_eq!(done, pixels.len() / 8 * 8);
+        for (p, d) in pixels.iter().zip(dst.chunks(3)).take(done) {
+            let mut p = *p;
+            if N == 3 {
+                p[3] = 255;
+            }
+            assert_eq!(d, &format.from(rgb, p)[..3], "{:?}", format);
+        }
+        let src: Vec<u8> = pixels.iter().flat_map(|p| p[..3].to_vec()).collect();
+        let mut dst = vec![0; pixels.len() * N];
+        let done = unsafe { lanes_to_rgb::<S, K, N>(f, kernel, &src, &mut dst) };
+        for (p, d) in pixels.iter().zip(dst.chunks(N)).take(done) {
+            let p = rgb.from(format, [p[0], p[1], p[2], 255]);
+            assert_eq!(d, &p[..N], "{:?}", format);
+        }
+    }
+
+    /// Check each kernel with `S`.
+    fn check_simd<S: Simd>() {
+        check_lanes::<S, _, 3>(YCbCr, Jpeg);
+        check_lanes::<S, _, 4>(YCbCr, Jpeg);
+        check_lanes::<S, _, 3>(YuvItur, Itur);
+        check_lanes::<S, _, 4>(YuvItur, Itur);
+        check_lanes::<S, _, 3>(YuvNtsc, Ntsc);
+        check_lanes::<S, _, 4>(YuvNtsc, Ntsc);
+        for &format in &YCBCR[3..] {
+            check_lanes::<S, _, 3>(format, Matrix::new(format));
+            check_lanes::<S, _, 4>(format, Matrix::new(format));
+        }
+    }
+
+    #[test]
+    fn simd_matches_from() {
+        // `matches_from` only checks the best SIMD the CPU has.
+        check_simd::<Scalar>();
+        #[cfg(target_arch = "x86_64")]
+        check_simd::<Sse2>();
+    }
+
+    /// Slow without optimizations, run with
+    /// `cargo test --release -- --ignored`.
+    #[test]
+    #[ignore]
+    fn matches_from_all_colors() {
+        // Every 24-bit color, to and from each YCbCr format.
+        let pixels: Vec<[u8; 4]> = (0..1u32 << 24)
+            .map(|i| [i as u8, (i >> 8) as u8, (i >> 16) as u8, 255])
+            .collect();
+        for &format in &YCBCR {
+            check(Srgb, format, &pixels);
+            check(format, Srgb, &pixels);
+        }
+    }
+}
@@ -0,0 +1,184 @@
+// Copyright Jeron Lau 2017 - 2018.
+// Dual-licensed under either the MIT License or the Boost Software License, Version 1.0.
+// (See accompanying file LICENSE_1_0.txt or copy at https://www.boost.org/LICENSE_1_0.txt)
+
+/// How each color component of a `VFrame` is stored.  Components are stored
+/// in native byte order, and integers go from 0 (none) to their max (full).
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum Depth {
+    /// 8-bit unsigned integer.
+    #[default]
+    U8,
+    /// 16-bit unsigned integer (also used for 10 and 12-bit formats, scaled
+    /// up to fill 16 bits).
+    U16,
+    /// 16-bit (half precision) float (0.0 to 1.0, can go higher for HDR).
+    F16,
+    /// 32-bit float (0.0 to 1.0, can go higher for HDR).
+    F32,
+}
+
+impl Depth {
+    /// Return the number of bits in a component.
+    pub fn bits(self) -> u32 {
+        match self {
+            Depth::U8 => 8,
+            Depth::U16 | Depth::F16 => 16,
+            Depth::F32 => 32,
+        }
+    }
+
+    /// Return the number of bytes in a component.
+    pub fn bytes(self) -> usize {
+        self.bits() as usize / 8
+    }
+
+    /// Return true if the components are floating point.
+    pub fn is_float(self) -> bool {
+        matches!(self, Depth::F16 | Depth::F32)
+    }
+
+    /// Get component number `index` of `data` as a float (0.0 to 1.0 for
+    /// integers).
+    pub fn get(self, data: &[u8], index: usize) -> f32 {
+        let i = index * self.bytes();
+        match self {
+            Depth::U8 => data[i] as f32 / 255.0,
+            Depth::U16 => u16::from_ne_bytes([data[i], data[i + 1]]) as f32 / 65535.0,
+            Depth::F16 => f16_to_f32(u16::from_ne_bytes([data[i], data[i + 1]])),
+            Depth::F32 => f32::from_ne_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]),
+        }
+    }
+
+    /// Set component number `index` of `data` from a float, rounding and
+    /// clipping for integers.
+    pub fn set(self, data: &mut [u8], index: usize, value: f32) {
+        let i = index * self.bytes();
+        let int = |max: f32| (value * max).round().max(0.0).min(max);
+        match self {
+            Depth::U8 => data[i] = int(255.0) as u8,
+            Depth::U16 => data[i..i + 2].copy_from_slice(&(int(65535.0) as u16).to_ne_bytes()),
+            Depth::F16 => data[i..i + 2].copy_from_slice(&f32_to_f16(value).to_ne_bytes()),
+            Depth::F32 => data[i..i + 4].copy_from_slice(&value.to_ne_bytes()),
+        }
+    }
+
+    /// Convert components to this depth from another.  Widening conversions
+    /// are lossless, narrowing conversions round and clip.
+    pub fn from(self, from: Depth, data: &[u8]) -> Vec<u8> {
+        let len = data.len() / from.bytes();
+        match (from, self) {
+            (from, to) if from == to => data.to_vec(),
+            // Fast path: x * 257 maps 0-255 onto 0-65535 exactly.
+            (Depth::U8, Depth::U16) => data
+                .iter()
+                .flat_map(|&c| (c as u16 * 257).to_ne_bytes())
+                .collect(),
+            _ => {
+                let mut out = vec![0; len * self.bytes()];
+                for i in 0..len {
+                    self.set(&mut out, i, from.get(data, i));
+                }
+                out
+            }
+        }
+    }
+}
+
+/// Convert half precision float bits to a float.
+fn f16_to_f32(h: u16) -> f32 {
+    let sign = ((h & 0x8000) as u32) << 16;
+    let exp = ((h >> 10) & 0x1f) as u32;
+    let man = (h & 0x3ff) as u32;
+    match exp {
+        // Zero or subnormal: man * 2^-24.
+        0 => {
+            let v = man as f32 / 16_777_216.0;
+            f32::from_bits(v.to_bits() | sign)
+        }
+        // Infinity or NaN.
+        0x1f => f32::from_bits(sign | 0x7f80_0000 | (man << 13)),
+        _ => f32::from_bits(sign | ((exp + 112) << 23) | (man << 13)),
+    }
+}
+
+/// Convert a float to half precision float bits, rounding to nearest even.
+fn f32_to_f16(v: f32) -> u16 {
+    let bits = v.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exp = ((bits >> 23) & 0xff) as i32;
+    let man = bits & 0x7f_ffff;
+    if exp == 0xff {
+        // Infinity or NaN (keeping NaN a NaN).
+        return sign | 0x7c00 | if man != 0 { 0x200 } else { 0 };
+    }
+    let e = exp - 127 + 15;
+    if e >= 0x1f {
+        return sign | 0x7c00;
+    }
+    let (half, shift, man) = if e <= 0 {
+        if e < -10 {
+            return sign;
+        }
+        // Subnormal: add the implicit 1, and shift it into place.
+        let man = man | 0x80_0000;
+        let shift = (14 - e) as u32;
+        (man >> shift, shift, man)
+    } else {
+        (((e as u32) << 10) | (man >> 13), 13, man)
+    };
+    let round = man & (1 << (shift - 1)) != 0;
+    let sticky = man & ((1 << (shift - 1)) - 1) != 0;
+    // A carry out of the mantissa correctly bumps the exponent.
+    let half = if round && (sticky || half & 1 != 0) {
+        half + 1
+    } else {
+        half
+    };
+    sign | half as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn half_floats() {
+        for &(f, h) in &[
+            (0.0, 0x0000),
+            (-0.0, 0x8000),
+            (1.0, 0x3c00),
+            (0.5, 0x3800),
+            (-2.0, 0xc000),
+            (65504.0, 0x7bff),
+            (1.0 / 16_777_216.0, 0x0001),
+            (f32::INFINITY, 0x7c00),
+        ] {
+            assert_eq!(f32_to_f16(f), h);
+            assert_eq!(f16_to_f32(h), f);
+        }
+        assert_eq!(f32_to_f16(1.0e6), 0x7c00);
+        assert!(f16_to_f32(f32_to_f16(f32::NAN)).is_nan());
+        // 1 + 2^-11 is halfway, and rounds to even.
+        assert_eq!(f32_to_f16(1.0 + 1.0 / 2048.0), 0x3c00);
+    }
+
+    #[test]
+    fn convert() {
+        let src: Vec<u8> = (0..=255).collect();
+        for &depth in &[Depth::U16, Depth::F16, Depth::F32] {
+            let wide = depth.from(Depth::U8, &src);
+            assert_eq!(wide.len(), 256 * depth.bytes());
+            assert_eq!(Depth::U8.from(depth, &wide), src);
+        }
+        let u16s = Depth::U16.from(Depth::U8, &[0, 128, 255]);
+        assert_eq!(Depth::U16.get(&u16s, 1), 128.0 * 257.0 / 65535.0);
+        let mut f = Depth::F32.from(Depth::U8, &[0, 0]);
+        Depth::F32.set(&mut f, 0, 2.0);
+        Depth::F32.set(&mut f, 1, -1.0);
+        assert_eq!(
+            Depth::U16.from(Depth::F32, &f),
+            Depth::U16.from(Depth::U8, &[255, 0])
+        );
+    }
+}
@@ -3,11 +3,23 @@
 // (See accompanying file LICENSE_1_0.txt or copy at https://www.boost.org/LICENSE_1_0.txt)
 
 mod blend;
+mod convert;
+mod depth;
+mod planar;
+mod simd;
+mod ycbcr;
 
 pub use self::blend::{blend, over};
+pub(crate) use self::convert::convert_u8;
+pub use self::depth::Depth;
+pub use self::planar::{ChromaFilter, ChromaSiting, PlanarFormat, PlanarFrame, Plane};
+pub use self::ycbcr::{YCbCrMatrix, YCbCrRange};
 
+use self::convert::{linear_to_s_u8, s_to_linear_u8};
 use std::collections::VecDeque;
-use std::mem::transmute;
+use Index;
+use Metadata;
+use Timebase;
 use VFrame;
 
 type Float = f32;
@@ -23,6 +35,29 @@ fn float_to_u8(a: Float) -> u8 {
     }
 }
 
+/// Convert floating point color channel to u8 color channel, rounding to
+/// the nearest value.
+#[inline(always)]
+fn round_u8(a: Float) -> u8 {
+    // The same as `(a * 255.0).round().clamp(0.0, 255.0) as u8` (with NaN as
+    // 0), but without float to int casts or `round()` so that it vectorizes:
+    // adding 2^23 rounds to the nearest integer (ties to even) in the low
+    // bits, and then ties are rounded up.
+    let a = a * 255.0;
+    let a = if a > 0.0 { a.min(255.0) } else { 0.0 };
+    let even = ((a + 8_388_608.0).to_bits() & 0xFF) as i32;
+    (even + (a - even as Float == 0.5) as i32) as u8
+}
+
+/// The same as `a as u8` (truncating, with saturation), but vectorizes (see
+/// `round_u8`).
+#[inline(always)]
+fn trunc_u8(a: f64) -> u8 {
+    let a = if a > 0.0 { a.min(255.0) } else { 0.0 };
+    let even = ((a + 4_503_599_627_370_496.0).to_bits() & 0xFF) as i32;
+    (even - (even as f64 > a) as i32) as u8
+}
+
 /// Convert u8 color channel to floating point color channel.
 fn u8_to_float(a: u8) -> Float {
     a as Float / 255.0 // range 0-255 => 0-1
@@ -37,11 +72,6 @@ fn s_to_linear(s: Float) -> Float {
     }
 }
 
-/// S space to Linear space for u8s
-fn s_to_linear_u8(s: u8) -> u8 {
-    float_to_u8(s_to_linear(u8_to_float(s)))
-}
-
 /// Linear space to S space
 fn linear_to_s(linear: Float) -> Float {
     if linear <= 0.0031308 {
@@ -51,16 +81,22 @@ fn linear_to_s(linear: Float) -> Float {
     }
 }
 
-/// Linear space to S space for u8s
-fn linear_to_s_u8(l: u8) -> u8 {
-    float_to_u8(linear_to_s(u8_to_float(l)))
-}
-
 /// Convert sRGBA to lHSVA
 fn srgba_to_lhsva(rgba: [u8; 4]) -> [u8; 4] {
-    let r = s_to_linear(u8_to_float(rgba[0]));
-    let g = s_to_linear(u8_to_float(rgba[1]));
-    let b = s_to_linear(u8_to_float(rgba[2]));
+    let [h, s, v, _] = srgba_to_lhsva_f32([
+        u8_to_float(rgba[0]),
+        u8_to_float(rgba[1]),
+        u8_to_float(rgba[2]),
+        1.0,
+    ]);
+    [float_to_u8(h), float_to_u8(s), float_to_u8(v), rgba[3]]
+}
+
+/// Convert sRGBA to lHSVA, as floats.
+fn srgba_to_lhsva_f32(rgba: [Float; 4]) -> [Float; 4] {
+    let r = s_to_linear(rgba[0]);
+    let g = s_to_linear(rgba[1]);
+    let b = s_to_linear(rgba[2]);
 
     let max = r.max(g).max(b);
     let min = r.min(g).min(b);
@@ -86,26 +122,32 @@ fn srgba_to_lhsva(rgba: [u8; 4]) -> [u8; 4] {
     let sat = if max == 0.0 { 0.0 } else { (max - min) / max };
     let val = max;
 
-    [
-        float_to_u8(hue),
-        float_to_u8(sat),
-        float_to_u8(val),
-        rgba[3],
-    ]
+    [hue, sat, val, rgba[3]]
 }
 
 /// Convert lHSVA to sRGBA
 fn lhsva_to_srgba(hsva: [u8; 4]) -> [u8; 4] {
-    let fh = u8_to_float(hsva[0]) * 6.0; // Get range 0-6
-    let h = fh as i8; // int 0-6
-    let s = u8_to_float(hsva[1]);
-    let v = u8_to_float(hsva[2]);
-
     if hsva[1] == 0 {
         // if saturation is 0, then it's gray
         return [hsva[2], hsva[2], hsva[2], hsva[3]];
     }
 
+    let [r, g, b, _] = lhsva_to_srgba_f32([
+        u8_to_float(hsva[0]),
+        u8_to_float(hsva[1]),
+        u8_to_float(hsva[2]),
+        1.0,
+    ]);
+    [float_to_u8(r), float_to_u8(g), float_to_u8(b), hsva[3]]
+}
+
+/// Convert lHSVA to sRGBA, as floats.
+fn lhsva_to_srgba_f32(hsva: [Float; 4]) -> [Float; 4] {
+    let fh = hsva[0] * 6.0; // Get range 0-6
+    let h = fh as i8; // int 0-6
+    let s = hsva[1];
+    let v = hsva[2];
+
     let f = fh - (h as Float); // difference from rounding to 0-6
     let p = v * (1.0 - s);
     let q = v * (1.0 - s * f);
@@ -120,16 +162,11 @@ fn lhsva_to_srgba(hsva: [u8; 4]) -> [u8; 4] {
         _ => (v, t, p), // 0 or 6
     };
 
-    [
-        float_to_u8(linear_to_s(r)),
-        float_to_u8(linear_to_s(g)),
-        float_to_u8(linear_to_s(b)),
-        hsva[3],
-    ]
+    [linear_to_s(r), linear_to_s(g), linear_to_s(b), hsva[3]]
 }
 
 /// The format for the color channels of the `Video`.
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
 #[repr(u8)]
 pub enum ColorChannels {
     /// Grayscale color format, 1 channel (stored in red channel).
@@ -137,6 +174,7 @@ pub enum ColorChannels {
     /// sRGB color format, 3 channels.
     Srgb = 3u8,
     /// sRGBA color format, 4 channels.
+    #[default]
     Srgba = 4u8,
 
     /// sBGR color format, 3 channels.
@@ -161,20 +199,26 @@ pub enum ColorChannels {
     /// Linear HSBA/HSVA, 4 channels.
     Lhsva = 4u8 + 20u8,
 
-    /// YCbCr color format, 3 channels
+    /// YCbCr color format (BT.601, full range, as in JPEG), 3 channels
     YCbCr = 3u8 + 25u8,
 
-    /// YUV ITU-R color format, 3 channels
+    /// YUV ITU-R color format (BT.601, full range), 3 channels
     YuvItur = 3u8 + 30u8,
 
-    /// YUV NTSC color format, 3 channels
+    /// YUV NTSC color format (BT.601, limited range), 3 channels
     YuvNtsc = 3u8 + 35u8,
-}
 
-impl Default for ColorChannels {
-    fn default() -> ColorChannels {
-        Srgba
-    }
+    /// YCbCr BT.709 (HD), full range, 3 channels
+    YCbCr709 = 3u8 + 40u8,
+
+    /// YCbCr BT.709 (HD), limited range, 3 channels
+    YCbCr709Limited = 3u8 + 45u8,
+
+    /// YCbCr BT.2020 (UHD), full range, 3 channels
+    YCbCr2020 = 3u8 + 50u8,
+
+    /// YCbCr BT.2020 (UHD), limited range, 3 channels
+    YCbCr2020Limited = 3u8 + 55u8,
 }
 
 pub use ColorChannels::*;
@@ -189,6 +233,17 @@ impl ColorChannels {
         }
     }
 
+    /// Convert a pixel to this format from another, with float components
+    /// of a frame stored at `depth` (which sets limited range YCbCr levels).
+    /// Values outside 0.0 to 1.0 (HDR) are kept.
+    pub fn from_f32(self, from: ColorChannels, depth: Depth, p: [f32; 4]) -> [f32; 4] {
+        if self == from {
+            p
+        } else {
+            self.srgba_to_f32(depth, from.to_srgba_f32(depth, p))
+        }
+    }
+
     /// Pack an sRGBA [u8; 4] into an sRGBA u32
     pub fn pack(p: [u8; 4]) -> u32 {
         let r = (p[0] as u32).rotate_right(0);
@@ -201,10 +256,11 @@ impl ColorChannels {
 
     /// Unpack an sRGBA u32 into an sRGBA [u8; 4]
     pub fn unpack(p: u32) -> [u8; 4] {
-        unsafe { transmute(p) }
+        p.to_ne_bytes()
     }
 
     /// Convert a pixel from sRGBA to this format.
+    #[inline(always)]
     fn srgba_to(self, p: [u8; 4]) -> [u8; 4] {
         let [r, g, b, a] = p;
         match self {
@@ -232,7 +288,7 @@ impl ColorChannels {
                 let y = (0.299 * r) + (0.587 * g) + (0.114 * b);
                 let cb = 128.0 - (0.168736 * r) - (0.331264 * g) + (0.5 * b);
                 let cr = 128.0 + (0.5 * r) - (0.418688 * g) - (0.081312 * b);
-                [y as u8, cb as u8, cr as u8, 255]
+                [trunc_u8(y), trunc_u8(cb), trunc_u8(cr), 255]
             }
             // From https://en.wikipedia.org/wiki/YUV#Y%E2%80%B2UV444_to_RGB888_conversion
             YuvItur => {
@@ -240,7 +296,7 @@ impl ColorChannels {
                 let y = (0.299 * r) + (0.587 * g) + (0.114 * b);
                 let cb = (-0.169 * r) - (0.331 * g) + (0.499 * b) + 128.0;
                 let cr = (0.499 * r) - (0.418 * g) - (0.0813 * b) + 128.0;
-                [y as u8, cb as u8, cr as u8, 255u8]
+                [trunc_u8(y), trunc_u8(cb), trunc_u8(cr), 255u8]
             }
             YuvNtsc => {
                 let [r, g, b] = [r as i32, g as i32, b as i32];
@@ -249,10 +305,16 @@ impl ColorChannels {
                 let cr = (((112 * r) + (-94 * g) + (-18 * b) + 128) >> 8) + 128;
                 [y as u8, cb as u8, cr as u8, 255u8]
             }
+            YCbCr709 | YCbCr709Limited | YCbCr2020 | YCbCr2020Limited => {
+                let p = [u8_to_float(r), u8_to_float(g), u8_to_float(b), 1.0];
+                let [y, cb, cr, _] = self.srgba_to_f32(Depth::U8, p);
+                [round_u8(y), round_u8(cb), round_u8(cr), 255]
+            }
         }
     }
 
     /// Convert a pixel in this format to sRGBA.
+    #[inline(always)]
     fn to_srgba(self, p: [u8; 4]) -> [u8; 4] {
         let [r, g, b, a] = p;
         match self {
@@ -277,42 +339,139 @@ impl ColorChannels {
                 let r = y + 1.402 * (cr - 128.0);
                 let g = y - 0.344136 * (cb - 128.0) - 0.714136 * (cr - 128.0);
                 let b = y + 1.772 * (cb - 128.0);
-                [r as u8, g as u8, b as u8, 255]
+                [trunc_u8(r), trunc_u8(g), trunc_u8(b), 255]
             }
             // From https://en.wikipedia.org/wiki/YUV#Y%E2%80%B2UV444_to_RGB888_conversion
             YuvItur => {
-                let [y, cb, cr] = [r, g - 128, b - 128];
+                let [y, cb, cr] = [r as i32, g as i32 - 128, b as i32 - 128];
                 let r = y + cr + (cr >> 2) + (cr >> 3) + (cr >> 5);
                 let g = y
                     - ((cb >> 2) + (cb >> 4) + (cb >> 5))
                     - ((cr >> 1) + (cr >> 3) + (cr >> 4) + (cr >> 5));
                 let b = y + cb + (cb >> 1) + (cb >> 2) + (cb >> 6);
-                [r, g, b, 255u8]
+                [
+                    r.clamp(0, 255) as u8,
+                    g.clamp(0, 255) as u8,
+                    b.clamp(0, 255) as u8,
+                    255u8,
+                ]
             }
             YuvNtsc => {
                 let [c, d, e] = [r as i32 - 16, g as i32 - 128, b as i32 - 128];
-                let r = (((298 * c) + (409 * e) + 128) >> 8).min(255).max(0);
-                let g = (((298 * c) + (-100 * d) + (-208 * e) + 128) >> 8)
-                    .min(255)
-                    .max(0);
-                let b = (((298 * c) + (516 * d) + 128) >> 8).min(255).max(0);
+                let r = (((298 * c) + (409 * e) + 128) >> 8).clamp(0, 255);
+                let g = (((298 * c) + (-100 * d) + (-208 * e) + 128) >> 8).clamp(0, 255);
+                let b = (((298 * c) + (516 * d) + 128) >> 8).clamp(0, 255);
                 [r as u8, g as u8, b as u8, 255u8]
             }
+            YCbCr709 | YCbCr709Limited | YCbCr2020 | YCbCr2020Limited => {
+                let p = [u8_to_float(r), u8_to_float(g), u8_to_float(b), 1.0];
+                let [r, g, b, _] = self.to_srgba_f32(Depth::U8, p);
+                [round_u8(r), round_u8(g), round_u8(b), 255]
+            }
+        }
+    }
+
+    /// Convert a float pixel from sRGBA to this format.
+    #[inline(always)]
+    fn srgba_to_f32(self, depth: Depth, p: [f32; 4]) -> [f32; 4] {
+        let [r, g, b, a] = p;
+        let l = s_to_linear;
+        match self {
+            Sgrayscale => [(r + g + b) / 3.0, 1.0, 1.0, 1.0],
+            Srgb => [r, g, b, 1.0],
+            Srgba => [r, g, b, a],
+            Sbgr => [b, g, r, 1.0],
+            Sbgra => [b, g, r, a],
+            Lgrayscale => [l((r + g + b) / 3.0), 1.0, 1.0, 1.0],
+            Lrgb => [l(r), l(g), l(b), 1.0],
+            Lrgba => [l(r), l(g), l(b), a],
+            Lbgr => [l(b), l(g), l(r), 1.0],
+            Lbgra => [l(b), l(g), l(r), a],
+            Lhsv => srgba_to_lhsva_f32([r, g, b, 1.0]),
+            Lhsva => srgba_to_lhsva_f32([r, g, b, a]),
+            YCbCr | YuvItur | YuvNtsc | YCbCr709 | YCbCr709Limited | YCbCr2020
+            | YCbCr2020Limited => {
+                let (matrix, range) = self.ycbcr_matrix().unwrap();
+                let [y, cb, cr] = matrix.to_ycbcr(range, depth, [r, g, b]);
+                [y, cb, cr, 1.0]
+            }
+        }
+    }
+
+    /// Convert a float pixel in this format to sRGBA.
+    #[inline(always)]
+    fn to_srgba_f32(self, depth: Depth, p: [f32; 4]) -> [f32; 4] {
+        let [r, g, b, a] = p;
+        let s = linear_to_s;
+        match self {
+            Sgrayscale => [r, r, r, 1.0],
+            Srgb => [r, g, b, 1.0],
+            Srgba => [r, g, b, a],
+            Sbgr => [b, g, r, 1.0],
+            Sbgra => [b, g, r, a],
+            Lgrayscale => [s(r), s(r), s(r), 1.0],
+            Lrgb => [s(r), s(g), s(b), 1.0],
+            Lrgba => [s(r), s(g), s(b), a],
+            Lbgr => [s(b), s(g), s(r), 1.0],
+            Lbgra => [s(b), s(g), s(r), a],
+            Lhsv => lhsva_to_srgba_f32([r, g, b, 1.0]),
+            Lhsva => lhsva_to_srgba_f32([r, g, b, a]),
+            YCbCr | YuvItur | YuvNtsc | YCbCr709 | YCbCr709Limited | YCbCr2020
+            | YCbCr2020Limited => {
+                let (matrix, range) = self.ycbcr_matrix().unwrap();
+                let [r, g, b] = matrix.to_rgb(range, depth, [r, g, b]);
+                [r, g, b, 1.0]
+            }
+        }
+    }
+
+    /// Get the YCbCr format for a matrix and range.
+    pub fn ycbcr(matrix: YCbCrMatrix, range: YCbCrRange) -> ColorChannels {
+        match (matrix, range) {
+            (YCbCrMatrix::Bt601, YCbCrRange::Full) => YCbCr,
+            (YCbCrMatrix::Bt601, YCbCrRange::Limited) => YuvNtsc,
+            (YCbCrMatrix::Bt709, YCbCrRange::Full) => YCbCr709,
+            (YCbCrMatrix::Bt709, YCbCrRange::Limited) => YCbCr709Limited,
+            (YCbCrMatrix::Bt2020, YCbCrRange::Full) => YCbCr2020,
+            (YCbCrMatrix::Bt2020, YCbCrRange::Limited) => YCbCr2020Limited,
         }
     }
 
+    /// Get the matrix and range of a YCbCr format, or `None` if it isn't
+    /// one.
+    pub fn ycbcr_matrix(self) -> Option<(YCbCrMatrix, YCbCrRange)> {
+        Some(match self {
+            YCbCr | YuvItur => (YCbCrMatrix::Bt601, YCbCrRange::Full),
+            YuvNtsc => (YCbCrMatrix::Bt601, YCbCrRange::Limited),
+            YCbCr709 => (YCbCrMatrix::Bt709, YCbCrRange::Full),
+            YCbCr709Limited => (YCbCrMatrix::Bt709, YCbCrRange::Limited),
+            YCbCr2020 => (YCbCrMatrix::Bt2020, YCbCrRange::Full),
+            YCbCr2020Limited => (YCbCrMatrix::Bt2020, YCbCrRange::Limited),
+            _ => return None,
+        })
+    }
+
     /// Return the number of channels.
     #[inline(always)]
     pub fn n_channels(self) -> usize {
         (self as u8 % 5) as usize
     }
+
+    /// Return true if there's an alpha channel.
+    pub fn has_alpha(self) -> bool {
+        self.n_channels() == 4
+    }
 }
 
-/// A Video Buffer (24fps).
+/// A Video Buffer (24fps unless set otherwise).
 pub struct Video {
+    /// Title, artist, cover art, etc.
+    pub metadata: Metadata,
     format: ColorChannels,
+    depth: Depth,
     wh: (u16, u16),
     n_frames: u32, // number of frames in the whole video.
+    timebase: Timebase,
     frames: VecDeque<VFrame>,
 }
 
@@ -320,13 +479,36 @@ impl Video {
     /// Create a new video buffer.
     pub fn new(format: ColorChannels, wh: (u16, u16), n_frames: u32) -> Self {
         Video {
+            metadata: Metadata::new(),
             wh,
             n_frames,
             format,
+            depth: Depth::U8,
+            timebase: Timebase::default(),
             frames: VecDeque::new(),
         }
     }
 
+    /// Get the frame rate of the video.
+    pub fn timebase(&self) -> Timebase {
+        self.timebase
+    }
+
+    /// Set the frame rate of the video.
+    pub fn set_timebase(&mut self, timebase: Timebase) {
+        self.timebase = timebase;
+    }
+
+    /// Get the `Index` of a frame number at this video's frame rate.
+    pub fn index(&self, frame: u32) -> Index {
+        Index::new(frame, self.timebase)
+    }
+
+    /// Get the duration of the whole video in seconds.
+    pub fn duration(&self) -> f64 {
+        self.timebase.to_seconds(self.n_frames as i64)
+    }
+
     /// Get the width and height of the video.
     pub fn wh(&self) -> (u16, u16) {
         self.wh
@@ -337,8 +519,54 @@ impl Video {
         self.format
     }
 
-    /// Add frame to the buffer.
+    /// Get how the color components of frames are stored (`Depth::U8`
+    /// unless set otherwise).
+    pub fn depth(&self) -> Depth {
+        self.depth
+    }
+
+    /// Set how the color components of frames are stored, converting the
+    /// frames in the buffer.
+    pub fn set_depth(&mut self, depth: Depth) {
+        for frame in &mut self.frames {
+            *frame = frame.to_depth(self.depth, depth);
+        }
+        self.depth = depth;
+    }
+
+    /// Convert the frames in the buffer to another `ColorChannels`, and
+    /// use it for frames added after.
+    pub fn convert(&mut self, format: ColorChannels) {
+        for frame in &mut self.frames {
+            *frame = frame.to_format(self.format, format, self.depth);
+        }
+        self.format = format;
+    }
+
+    /// Create a new video buffer with the frames in this one converted to
+    /// another `ColorChannels`.
+    pub fn to_format(&self, format: ColorChannels) -> Video {
+        Video {
+            metadata: self.metadata.clone(),
+            format,
+            depth: self.depth,
+            wh: self.wh,
+            n_frames: self.n_frames,
+            timebase: self.timebase,
+            frames: self
+                .frames
+                .iter()
+                .map(|f| f.to_format(self.format, format, self.depth))
+                .collect(),
+        }
+    }
+
+    /// Add frame to the buffer.  Panics if the frame's length doesn't match
+    /// the video's size, format and depth.
     pub fn add(&mut self, data: VFrame) {
+        let (w, h) = self.wh;
+        let len = w as usize * h as usize * self.n_channels() * self.depth.bytes();
+        assert_eq!(data.data.len(), len, "VFrame length doesn't match Video");
         self.frames.push_back(data);
     }
 
@@ -347,9 +575,14 @@ impl Video {
         self.frames.len() as u32
     }
 
+    /// Return true if there are no frames in the buffer.
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
     /// Returns pixels for the next frame on the Queue.
     pub fn pop(&mut self) -> Option<VFrame> {
-        Some(self.frames.pop_front()?)
+        self.frames.pop_front()
     }
 
     /// Return the number of channels.
@@ -413,4 +646,114 @@ mod tests {
             assert_eq!(color, [255, 255, 255, a]);
         }
     }
+
+    #[test]
+    fn rounding_matches_casts() {
+        let halves = (-4..520).map(|i| i as f32 / 2.0);
+        let special = [f32::NAN, f32::INFINITY, -f32::INFINITY, -0.0, 0.49999997];
+        let all = (0..=u32::MAX).step_by(997).map(f32::from_bits);
+        for a in halves.chain(special.iter().cloned()).chain(all) {
+            let x = a / 255.0;
+            assert_eq!(round_u8(x), (x * 255.0).round().clamp(0.0, 255.0) as u8);
+            let a = a as f64;
+            assert_eq!(trunc_u8(a), a as u8, "{}", a);
+            assert_eq!(trunc_u8(a + 0.999_999_999), (a + 0.999_999_999) as u8);
+        }
+    }
+
+    #[test]
+    fn float_matches_u8() {
+        for &format in &[Lrgba, Lhsva, YCbCr, YuvNtsc, Sgrayscale] {
+            for &p in &[[255, 0, 0, 255], [10, 128, 250, 64], [255, 255, 255, 0]] {
+                let f = [0, 1, 2, 3].map(|i| p[i] as f32 / 255.0);
+                let there = format.from_f32(Srgba, Depth::F32, f);
+                let back = Srgba.from_f32(format, Depth::F32, there);
+                let u8s = format.from(Srgba, p);
+                for i in 0..format.n_channels() {
+                    assert!((there[i] * 255.0 - u8s[i] as f32).abs() <= 1.5);
+                }
+                if format != Sgrayscale {
+                    for i in 0..3 {
+                        assert!((back[i] - f[i]).abs() < 0.01);
+                    }
+                }
+                assert_eq!(back[3], if format.has_alpha() { f[3] } else { 1.0 });
+            }
+        }
+    }
+
+    #[test]
+    fn set_depth() {
+        let mut video = Video::new(Srgba, (1, 1), 1);
+        video.add(VFrame::new(vec![0, 51, 255, 128]));
+        video.set_depth(Depth::F32);
+        let mut frame = video.pop().unwrap();
+        assert_eq!(frame.get_f32(Depth::F32, 0), [0.0, 0.2, 1.0, 128.0 / 255.0]);
+        frame.set_f32(Depth::F32, 0, [1.5, -0.5, 0.5, 1.0]);
+        assert_eq!(
+            frame.to_depth(Depth::F32, Depth::U8).get(0),
+            [255, 0, 128, 255]
+        );
+    }
+
+    #[test]
+    fn ycbcr_matrices() {
+        // Used to overflow.
+        for &p in &[[0, 0, 0, 255], [255, 0, 255, 255], [0, 255, 0, 255]] {
+            Srgba.from(YuvItur, p);
+        }
+        let limited = ColorChannels::ycbcr(YCbCrMatrix::Bt709, YCbCrRange::Limited);
+        assert_eq!(limited, YCbCr709Limited);
+        assert_eq!(
+            limited.from(Srgba, [255, 255, 255, 255]),
+            [235, 128, 128, 255]
+        );
+        assert_eq!(limited.from(Srgba, [0, 0, 0, 255]), [16, 128, 128, 255]);
+        let red = limited.from(Srgba, [255, 0, 0, 255]);
+        assert_eq!(red, [63, 102, 240, 255]);
+        let back = Srgba.from(limited, red);
+        for (c, want) in back.iter().zip(&[255, 0, 0, 255]) {
+            assert!((*c as i16 - want).abs() <= 1);
+        }
+        assert_eq!(
+            YuvNtsc.ycbcr_matrix(),
+            Some((YCbCrMatrix::Bt601, YCbCrRange::Limited))
+        );
+        assert_eq!(Srgb.ycbcr_matrix(), None);
+    }
+
+    #[test]
+    fn convert_video() {
+        let mut video = Video::new(Sbgra, (2, 1), 1);
+        video.add(VFrame::new(vec![1, 2, 3, 4, 50, 60, 70, 80]));
+        let srgb = video.to_format(Srgb);
+        video.convert(Srgb);
+        assert_eq!((video.format(), video.n_channels()), (Srgb, 3));
+        for mut video in [video, srgb] {
+            assert_eq!(video.pop().unwrap().data, vec![3, 2, 1, 70, 60, 50]);
+        }
+
+        let mut video = Video::new(Srgb, (1, 1), 1);
+        video.set_depth(Depth::U16);
+        video.add(VFrame::new(Depth::U16.from(Depth::U8, &[10, 20, 30])));
+        video.convert(Sbgra);
+        let frame = video.pop().unwrap();
+        assert_eq!(frame.data.len(), 8);
+        assert_eq!(
+            Depth::U8.from(Depth::U16, &frame.data),
+            vec![30, 20, 10, 255]
+        );
+        // 10-bit limited range black (64 << 6), not 16 / 255 of full scale.
+        let black = VFrame::new(Depth::U16.from(Depth::U8, &[0, 0, 0]));
+        let ycbcr = black.to_format(Srgb, YCbCr2020Limited, Depth::U16);
+        assert_eq!(&ycbcr.data[..2], &4096u16.to_ne_bytes());
+    }
+
+    #[test]
+    #[should_panic(expected = "VFrame length doesn't match Video")]
+    fn add_checks_depth() {
+        let mut video = Video::new(Srgba, (1, 1), 1);
+        video.set_depth(Depth::U16);
+        video.add(VFrame::new(vec![0, 0, 0, 255]));
+    }
 }
@@ -0,0 +1,394 @@
+// Copyright Jeron Lau 2017 - 2018.
+// Dual-licensed under either the MIT License or the Boost Software License, Version 1.0.
+// (See accompanying file LICENSE_1_0.txt or copy at https://www.boost.org/LICENSE_1_0.txt)
+
+use ColorChannels;
+use Timestamp;
+use VFrame;
+
+/// How the luma (Y) and chroma (Cb, Cr) samples of a `PlanarFrame` are
+/// subsampled and laid out.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PlanarFormat {
+    /// 4:2:0, with Y, Cb and Cr planes.
+    I420,
+    /// 4:2:0, with a Y plane and an interleaved CbCr plane.
+    Nv12,
+    /// 4:2:2, packed into one plane as Y0 Cb Y1 Cr.
+    Yuy2,
+    /// 4:2:2, with Y, Cb and Cr planes.
+    I422,
+    /// 4:4:4 (no subsampling), with Y, Cb and Cr planes.
+    I444,
+}
+
+impl PlanarFormat {
+    /// Return how many luma samples there are for each chroma sample, across
+    /// and down.
+    pub fn subsampling(self) -> (usize, usize) {
+        match self {
+            PlanarFormat::I420 | PlanarFormat::Nv12 => (2, 2),
+            PlanarFormat::Yuy2 | PlanarFormat::I422 => (2, 1),
+            PlanarFormat::I444 => (1, 1),
+        }
+    }
+
+    /// Return the number of planes.
+    pub fn n_planes(self) -> usize {
+        match self {
+            PlanarFormat::Yuy2 => 1,
+            PlanarFormat::Nv12 => 2,
+            PlanarFormat::I420 | PlanarFormat::I422 | PlanarFormat::I444 => 3,
+        }
+    }
+}
+
+/// Where chroma samples sit, compared to the luma samples they cover.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum ChromaSiting {
+    /// Lined up with the left luma sample, and centered vertically (MPEG-2,
+    /// H.264 and most video).
+    #[default]
+    Left,
+    /// Centered between the luma samples (JPEG and MPEG-1).
+    Center,
+    /// Lined up with the top left luma sample (BT.2020 and 4:2:2 video).
+    TopLeft,
+}
+
+/// How chroma is resampled when converting a `PlanarFrame`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum ChromaFilter {
+    /// Use the nearest sample (fast, but blocky).
+    Nearest,
+    /// Blend the nearby samples (a tent filter when downsampling).
+    #[default]
+    Bilinear,
+}
+
+/// One plane of a `PlanarFrame`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Plane {
+    /// The samples, row by row.
+    pub data: Vec<u8>,
+    /// The number of bytes from the start of one row to the next (can be
+    /// more than the row's width for padding).
+    pub stride: usize,
+}
+
+/// An 8-bit video frame of chroma-subsampled planar (or packed) YCbCr, as
+/// handed out by video codecs.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PlanarFrame {
+    /// How the samples are laid out.
+    pub format: PlanarFormat,
+    /// The YCbCr format of each (Y, Cb, Cr) sample, like
+    /// `ColorChannels::YCbCr`.
+    pub colors: ColorChannels,
+    /// Where chroma samples sit.
+    pub siting: ChromaSiting,
+    /// The planes: Y, then Cb and Cr (see `PlanarFormat`).
+    pub planes: Vec<Plane>,
+    /// Presentation timestamp, `None` if the frame is shown at its index.
+    pub pts: Option<Timestamp>,
+    wh: (u16, u16),
+}
+
+impl PlanarFrame {
+    /// Create a black frame, with no padding after rows.
+    pub fn new(format: PlanarFormat, colors: ColorChannels, wh: (u16, u16)) -> PlanarFrame {
+        PlanarFrame::with_strides(format, colors, wh, &[])
+    }
+
+    /// Create a black frame, with the stride of each plane (strides that
+    /// are missing or too small are made just big enough).
+    pub fn with_strides(
+        format: PlanarFormat,
+        colors: ColorChannels,
+        wh: (u16, u16),
+        strides: &[usize],
+    ) -> PlanarFrame {
+        let mut frame = PlanarFrame {
+            format,
+            colors,
+            siting: ChromaSiting::default(),
+            planes: Vec::new(),
+            pts: None,
+            wh,
+        };
+        let black = colors.from(ColorChannels::Srgba, [0, 0, 0, 255]);
+        for i in 0..format.n_planes() {
+            let (width, height) = frame.plane_wh(i);
+            let stride = strides.get(i).map_or(width, |&s| s.max(width));
+            frame.planes.push(Plane {
+                data: vec![0; stride * height],
+                stride,
+            });
+        }
+        let (cw, ch) = frame.chroma_wh();
+        for y in 0..wh.1 as usize {
+            for x in 0..wh.0 as usize {
+                frame.set_luma(x, y, black[0]);
+            }
+        }
+        for y in 0..ch {
+            for x in 0..cw {
+                frame.set_chroma(x, y, [black[1], black[2]]);
+            }
+        }
+        frame
+    }
+
+    /// Get the width and height in luma samples.
+    pub fn wh(&self) -> (u16, u16) {
+        self.wh
+    }
+
+    /// Get the width and height in chroma samples (rounded up).
+    pub fn chroma_wh(&self) -> (usize, usize) {
+        let (sx, sy) = self.format.subsampling();
+        let (w, h) = (self.wh.0 as usize, self.wh.1 as usize);
+        (w.div_ceil(sx), h.div_ceil(sy))
+    }
+
+    /// Get the width (in bytes, without padding) and height of a plane.
+    pub fn plane_wh(&self, plane: usize) -> (usize, usize) {
+        let (cw, ch) = self.chroma_wh();
+        let (w, h) = (self.wh.0 as usize, self.wh.1 as usize);
+        match (self.format, plane) {
+            (PlanarFormat::Yuy2, _) => (cw * 4, h),
+            (_, 0) => (w, h),
+            (PlanarFormat::Nv12, _) => (cw * 2, ch),
+            _ => (cw, ch),
+        }
+    }
+
+    /// Get the luma (Y) sample at `x`, `y`.
+    pub fn luma(&self, x: usize, y: usize) -> u8 {
+        let plane = &self.planes[0];
+        match self.format {
+            PlanarFormat::Yuy2 => plane.data[y * plane.stride + x * 2],
+            _ => plane.data[y * plane.stride + x],
+        }
+    }
+
+    /// Set the luma (Y) sample at `x`, `y`.
+    pub fn set_luma(&mut self, x: usize, y: usize, luma: u8) {
+        let plane = &mut self.planes[0];
+        match self.format {
+            PlanarFormat::Yuy2 => plane.data[y * plane.stride + x * 2] = luma,
+            _ => plane.data[y * plane.stride + x] = luma,
+        }
+    }
+
+    /// Get the chroma (Cb, Cr) sample at `x`, `y` (in chroma samples).
+    pub fn chroma(&self, x: usize, y: usize) -> [u8; 2] {
+        let (i, j) = self.chroma_at(x, y);
+        [self.planes[i.0].data[i.1], self.planes[j.0].data[j.1]]
+    }
+
+    /// Set the chroma (Cb, Cr) sample at `x`, `y` (in chroma samples).
+    pub fn set_chroma(&mut self, x: usize, y: usize, chroma: [u8; 2]) {
+        let (i, j) = self.chroma_at(x, y);
+        self.planes[i.0].data[i.1] = chroma[0];
+        self.planes[j.0].data[j.1] = chroma[1];
+    }
+
+    /// Get the plane and byte of the Cb and Cr samples at `x`, `y`.
+    fn chroma_at(&self, x: usize, y: usize) -> ((usize, usize), (usize, usize)) {
+        match self.format {
+            PlanarFormat::Yuy2 => {
+                let at = y * self.planes[0].stride + x * 4;
+                ((0, at + 1), (0, at + 3))
+            }
+            PlanarFormat::Nv12 => {
+                let at = y * self.planes[1].stride + x * 2;
+                ((1, at), (1, at + 1))
+            }
+            _ => (
+                (1, y * self.planes[1].stride + x),
+                (2, y * self.planes[2].stride + x),
+            ),
+        }
+    }
+
+    /// Get where chroma sample 0, 0 is, and the distance between chroma
+    /// samples, in luma samples.
+    fn chroma_grid(&self) -> ((f32, f32), (f32, f32)) {
+        let (sx, sy) = self.format.subsampling();
+        let (sx, sy) = (sx as f32, sy as f32);
+        let center = |s: f32| (s - 1.0) / 2.0;
+        let offset = match self.siting {
+            ChromaSiting::Left => (0.0, center(sy)),
+            ChromaSiting::Center => (center(sx), center(sy)),
+            ChromaSiting::TopLeft => (0.0, 0.0),
+        };
+        (offset, (sx, sy))
+    }
+
+    /// Set the frame from sRGBA pixels (4 bytes each, like `VFrame::get`),
+    /// downsampling chroma with `filter`.  The frame's `pts` is kept.
+    pub fn set_srgba(&mut self, frame: &VFrame, filter: ChromaFilter) {
+        let (w, h) = (self.wh.0 as usize, self.wh.1 as usize);
+        let mut ycbcr = Vec::with_capacity(w * h);
+        for i in 0..w * h {
+            ycbcr.push(self.colors.from(ColorChannels::Srgba, frame.get(i)));
+        }
+        for y in 0..h {
+            for x in 0..w {
+                self.set_luma(x, y, ycbcr[y * w + x][0]);
+            }
+        }
+
+        let ((ox, oy), (sx, sy)) = self.chroma_grid();
+        let (cw, ch) = self.chroma_wh();
+        for cy in 0..ch {
+            for cx in 0..cw {
+                let (px, py) = (ox + cx as f32 * sx, oy + cy as f32 * sy);
+                let chroma = match filter {
+                    ChromaFilter::Nearest => {
+                        let x = (px.round() as usize).min(w - 1);
+                        let y = (py.round() as usize).min(h - 1);
+                        let p = ycbcr[y * w + x];
+                        [p[1], p[2]]
+                    }
+                    ChromaFilter::Bilinear => {
+                        // Tent filter as wide as the subsampling, so each
+                        // luma sample is counted once overall.
+                        let (mut sum, mut total) = ([0.0f32; 2], 0.0);
+                        let range = |p: f32, s: f32, len: usize| {
+                            let lo = (p - s + 1.0).ceil().max(0.0) as usize;
+                            let hi = ((p + s - 1.0).floor() as usize).min(len - 1);
+                            lo..=hi
+                        };
+                        for y in range(py, sy, h) {
+                            let wy = 1.0 - (y as f32 - py).abs() / sy;
+                            for x in range(px, sx, w) {
+                                let weight = wy * (1.0 - (x as f32 - px).abs() / sx);
+                                let p = ycbcr[y * w + x];
+                                sum[0] += p[1] as f32 * weight;
+                                sum[1] += p[2] as f32 * weight;
+                                total += weight;
+                            }
+                        }
+                        [
+                            (sum[0] / total).round() as u8,
+                            (sum[1] / total).round() as u8,
+                        ]
+                    }
+                };
+                self.set_chroma(cx, cy, chroma);
+            }
+        }
+        self.pts = frame.pts;
+    }
+
+    /// Convert to an sRGBA `VFrame`, upsampling chroma with `filter`.
+    pub fn to_srgba(&self, filter: ChromaFilter) -> VFrame {
+        let (w, h) = (self.wh.0 as usize, self.wh.1 as usize);
+        let ((ox, oy), (sx, sy)) = self.chroma_grid();
+        let (cw, ch) = self.chroma_wh();
+        let mut out = VFrame {
+            data: vec![0; w * h * 4],
+            pts: self.pts,
+        };
+        for y in 0..h {
+            let cy = ((y as f32 - oy) / sy).max(0.0).min((ch - 1) as f32);
+            for x in 0..w {
+                let cx = ((x as f32 - ox) / sx).max(0.0).min((cw - 1) as f32);
+                let [cb, cr] = match filter {
+                    ChromaFilter::Nearest => self.chroma(cx.round() as usize, cy.round() as usize),
+                    ChromaFilter::Bilinear => {
+                        let (x0, y0) = (cx as usize, cy as usize);
+                        let (x1, y1) = ((x0 + 1).min(cw - 1), (y0 + 1).min(ch - 1));
+                        let (fx, fy) = (cx - x0 as f32, cy - y0 as f32);
+                        let [a, b, c, d] = [
+                            self.chroma(x0, y0),
+                            self.chroma(x1, y0),
+                            self.chroma(x0, y1),
+                            self.chroma(x1, y1),
+                        ];
+                        let lerp = |i: usize| {
+                            let top = a[i] as f32 + (b[i] as f32 - a[i] as f32) * fx;
+                            let bottom = c[i] as f32 + (d[i] as f32 - c[i] as f32) * fx;
+                            (top + (bottom - top) * fy).round() as u8
+                        };
+                        [lerp(0), lerp(1)]
+                    }
+                };
+                let p = [self.luma(x, y), cb, cr, 255];
+                out.set(y * w + x, ColorChannels::Srgba.from(self.colors, p));
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layouts() {
+        let frame =
+            PlanarFrame::with_strides(PlanarFormat::I420, ColorChannels::YCbCr, (5, 3), &[8, 0, 4]);
+        assert_eq!(frame.chroma_wh(), (3, 2));
+        let strides: Vec<usize> = frame.planes.iter().map(|p| p.stride).collect();
+        assert_eq!(strides, vec![8, 3, 4]);
+        assert_eq!((frame.luma(4, 2), frame.chroma(2, 1)), (0, [128, 128]));
+
+        for &format in &[
+            PlanarFormat::I420,
+            PlanarFormat::Nv12,
+            PlanarFormat::Yuy2,
+            PlanarFormat::I422,
+            PlanarFormat::I444,
+        ] {
+            let mut frame = PlanarFrame::new(format, ColorChannels::YCbCr, (4, 2));
+            assert_eq!(frame.planes.len(), format.n_planes());
+            frame.set_luma(3, 1, 7);
+            frame.set_chroma(1, 0, [1, 2]);
+            assert_eq!((frame.luma(3, 1), frame.chroma(1, 0)), (7, [1, 2]));
+            assert_eq!(frame.luma(2, 1), 0);
+        }
+    }
+
+    #[test]
+    fn srgba_round_trip() {
+        // Colors away from edges come back the same with any format, siting
+        // or filter.
+        let (w, h) = (8, 4);
+        let mut src = VFrame::new(vec![0; w * h * 4]);
+        for i in 0..w * h {
+            src.set(
+                i,
+                if i % w < 4 {
+                    [200, 30, 30, 255]
+                } else {
+                    [20, 90, 220, 255]
+                },
+            );
+        }
+        for &format in &[PlanarFormat::I420, PlanarFormat::Yuy2, PlanarFormat::I444] {
+            for &siting in &[
+                ChromaSiting::Left,
+                ChromaSiting::Center,
+                ChromaSiting::TopLeft,
+            ] {
+                for &filter in &[ChromaFilter::Nearest, ChromaFilter::Bilinear] {
+                    let mut planar =
+                        PlanarFrame::new(format, ColorChannels::YCbCr, (w as u16, h as u16));
+                    planar.siting = siting;
+                    planar.set_srgba(&src, filter);
+                    let out = planar.to_srgba(filter);
+                    for &x in &[0, 1, 6, 7] {
+                        let (a, b) = (src.get(x), out.get(x + w));
+                        for c in 0..3 {
+                            assert!((a[c] as i16 - b[c] as i16).abs() <= 3);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,829 @@
+// Copyright Jeron Lau 2017 - 2018.
+// Dual-licensed under either the MIT License or the Boost Software License, Version 1.0.
+// (See accompanying file LICENSE_1_0.txt or copy at https://www.boost.org/LICENSE_1_0.txt)
+
+//! SIMD math on 8 pixels at a time, from `std::arch` for each target: AVX2
+//! (when the CPU has it) and SSE2 on x86_64, and NEON on aarch64.  Other
+//! targets use plain arrays instead.
+//!
+//! These are the same IEEE operations as scalar code (there's no fused
+//! multiply-add), so they give exactly the same results.  The functions are
+//! unsafe because they're only safe to call when the CPU has the target
+//! feature, and are always inlined so that they're compiled with the
+//! features of the function that calls them.
+
+/// 8 lanes of f32s, f64s and i32s.
+pub(super) trait Simd: Copy {
+    /// 8 f32s.
+    type F: Copy;
+    /// 8 f64s.
+    type D: Copy;
+    /// 8 i32s.
+    type I: Copy;
+
+    /// Load 8 pixels with `N` (3 or 4) channels, getting the first 3
+    /// channels.
+    unsafe fn load<const N: usize>(p: &[u8]) -> [Self::I; 3];
+    /// Store 8 pixels with `N` (3 or 4) channels, from 3 channels of 0 to
+    /// 255 (and 255 for the 4th).
+    unsafe fn store<const N: usize>(c: [Self::I; 3], p: &mut [u8]);
+
+    unsafe fn splat(a: i32) -> Self::I;
+    unsafe fn add(a: Self::I, b: Self::I) -> Self::I;
+    unsafe fn sub(a: Self::I, b: Self::I) -> Self::I;
+    /// Multiply, where both `a` and `b` fit in an i16.
+    unsafe fn mul(a: Self::I, b: Self::I) -> Self::I;
+    /// Arithmetic shift right.
+    unsafe fn shr(a: Self::I, n: i32) -> Self::I;
+    unsafe fn min(a: Self::I, b: Self::I) -> Self::I;
+    unsafe fn max(a: Self::I, b: Self::I) -> Self::I;
+
+    unsafe fn splat_f(a: f32) -> Self::F;
+    unsafe fn add_f(a: Self::F, b: Self::F) -> Self::F;
+    unsafe fn sub_f(a: Self::F, b: Self::F) -> Self::F;
+    unsafe fn mul_f(a: Self::F, b: Self::F) -> Self::F;
+    unsafe fn div_f(a: Self::F, b: Self::F) -> Self::F;
+    unsafe fn min_f(a: Self::F, b: Self::F) -> Self::F;
+    unsafe fn max_f(a: Self::F, b: Self::F) -> Self::F;
+    unsafe fn to_f(a: Self::I) -> Self::F;
+    /// Truncate towards zero (in range of an i32).
+    unsafe fn trunc_f(a: Self::F) -> Self::I;
+    /// -1 where `a >= b`, 0 elsewhere.
+    unsafe fn ge_f(a: Self::F, b: Self::F) -> Self::I;
+
+    unsafe fn splat_d(a: f64) -> Self::D;
+    unsafe fn add_d(a: Self::D, b: Self::D) -> Self::D;
+    unsafe fn sub_d(a: Self::D, b: Self::D) -> Self::D;
+    unsafe fn mul_d(a: Self::D, b: Self::D) -> Self::D;
+    unsafe fn min_d(a: Self::D, b: Self::D) -> Self::D;
+    unsafe fn max_d(a: Self::D, b: Self::D) -> Self::D;
+    unsafe fn to_d(a: Self::I) -> Self::D;
+    /// Truncate towards zero (in range of an i32).
+    unsafe fn trunc_d(a: Self::D) -> Self::I;
+}
+
+#[cfg(target_arch = "x86_64")]
+pub(super) use self::x86::{Avx2, Sse2};
+
+#[cfg(any(test, not(any(target_arch = "x86_64", target_arch = "aarch64"))))]
+pub(super) use self::scalar::Scalar;
+
+#[cfg(target_arch = "aarch64")]
+pub(super) use self::arm::Neon;
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use super::Simd;
+    use std::arch::x86_64::*;
+
+    /// AVX2, which must be checked for with `is_x86_feature_detected!`.
+    #[derive(Copy, Clone)]
+    pub(in super::super) struct Avx2;
+
+    impl Simd for Avx2 {
+        type F = __m256;
+        type D = (__m256d, __m256d);
+        type I = __m256i;
+
+        #[inline(always)]
+        unsafe fn load<const N: usize>(p: &[u8]) -> [__m256i; 3] {
+            let p = &p[..8 * N];
+            let v = if N == 4 {
+                _mm256_loadu_si256(p.as_ptr() as *const __m256i)
+            } else {
+                // Move the last 4 pixels to the high 128 bits.
+                let low = _mm_loadu_si128(p.as_ptr() as *const __m128i);
+                let high = _mm_loadl_epi64(p[16..].as_ptr() as *const __m128i);
+                let v = _mm256_set_m128i(high, low);
+                _mm256_permutevar8x32_epi32(v, _mm256_setr_epi32(0, 1, 2, 3, 3, 4, 5, 6))
+            };
+            // Shuffle each channel into the low byte of each 32 bits.
+            let n = N as i8;
+            let mut c = [_mm256_setzero_si256(); 3];
+            for (i, c) in c.iter_mut().enumerate() {
+                let mut m = [-1i8; 32];
+                for k in 0..8 {
+                    m[4 * k] = n * (k as i8 % 4) + i as i8;
+                }
+                let m = _mm256_loadu_si256(m.as_ptr() as *const __m256i);
+                *c = _mm256_shuffle_epi8(v, m);
+            }
+            c
+        }
+
+        #[inline(always)]
+        unsafe fn store<const N: usize>(c: [__m256i; 3], p: &mut [u8]) {
+            let p = &mut p[..8 * N];
+            let v = _mm256_or_si256(c[0], _mm256_slli_epi32(c[1], 8));
+            let v = _mm256_or_si256(v, _mm256_slli_epi32(c[2], 16));
+            if N == 4 {
+                let v = _mm256_or_si256(v, _mm256_set1_epi32(0xFF00_0000u32 as i32));
+                _mm256_storeu_si256(p.as_mut_ptr() as *mut __m256i, v);
+            } else {
+                // Pack 3 bytes of each 32 bits into the low 24 bytes.
+                let m = [0, 1, 2, 4, 5, 6, 8, 9, 10, 12, 13, 14, -1, -1, -1, -1i8];
+                let m = _mm256_broadcastsi128_si256(_mm_loadu_si128(m.as_ptr() as *const __m128i));
+                let v = _mm256_shuffle_epi8(v, m);
+                let v = _mm256_permutevar8x32_epi32(v, _mm256_setr_epi32(0, 1, 2, 4, 5, 6, 7, 7));
+                _mm_storeu_si128(p.as_mut_ptr() as *mut __m128i, _mm256_castsi256_si128(v));
+                let high = _mm256_extracti128_si256(v, 1);
+                _mm_storel_epi64(p[16..].as_mut_ptr() as *mut __m128i, high);
+            }
+        }
+
+        #[inline(always)]
+        unsafe fn splat(a: i32) -> __m256i {
+            _mm256_set1_epi32(a)
+        }
+        #[inline(always)]
+        unsafe fn add(a: __m256i, b: __m256i) -> __m256i {
+            _mm256_add_epi32(a, b)
+        }
+        #[inline(always)]
+        unsafe fn sub(a: __m256i, b: __m256i) -> __m256i {
+            _mm256_sub_epi32(a, b)
+        }
+        #[inline(always)]
+        unsafe fn mul(a: __m256i, b: __m256i) -> __m256i {
+            // Multiply the low 16 bits (the high 16 bits of `b` are 0).
+            _mm256_madd_epi16(a, _mm256_and_si256(b, _mm256_set1_epi32(0xFFFF)))
+        }
+        #[inline(always)]
+        unsafe fn shr(a: __m256i, n: i32) -> __m256i {
+            _mm256_sra_epi32(a, _mm_cvtsi32_si128(n))
+        }
+        #[inline(always)]
+        unsafe fn min(a: __m256i, b: __m256i) -> __m256i {
+            _mm256_min_epi32(a, b)
+        }
+        #[inline(always)]
+        unsafe fn max(a: __m256i, b: __m256i) -> __m256i {
+            _mm256_max_epi32(a, b)
+        }
+
+        #[inline(always)]
+        unsafe fn splat_f(a: f32) -> __m256 {
+            _mm256_set1_ps(a)
+        }
+        #[inline(always)]
+        unsafe fn add_f(a: __m256, b: __m256) -> __m256 {
+            _mm256_add_ps(a, b)
+        }
+        #[inline(always)]
+        unsafe fn sub_f(a: __m256, b: __m256) -> __m256 {
+            _mm256_sub_ps(a, b)
+        }
+        #[inline(always)]
+        unsafe fn mul_f(a: __m256, b: __m256) -> __m256 {
+            _mm256_mul_ps(a, b)
+        }
+        #[inline(always)]
+        unsafe fn div_f(a: __m256, b: __m256) -> __m256 {
+            _mm256_div_ps(a, b)
+        }
+        #[inline(always)]
+        unsafe fn min_f(a: __m256, b: __m256) -> __m256 {
+            _mm256_min_ps(a, b)
+        }
+        #[inline(always)]
+        unsafe fn max_f(a: __m256, b: __m256) -> __m256 {
+            _mm256_max_ps(a, b)
+        }
+        #[inline(always)]
+        unsafe fn to_f(a: __m256i) -> __m256 {
+            _mm256_cvtepi32_ps(a)
+        }
+        #[inline(always)]
+        unsafe fn trunc_f(a: __m256) -> __m256i {
+            _mm256_cvttps_epi32(a)
+        }
+        #[inline(always)]
+        unsafe fn ge_f(a: __m256, b: __m256) -> __m256i {
+            _mm256_castps_si256(_mm256_cmp_ps(a, b, _CMP_GE_OQ))
+        }
+
+        #[inline(always)]
+        unsafe fn splat_d(a: f64) -> Self::D {
+            (_mm256_set1_pd(a), _mm256_set1_pd(a))
+        }
+        #[inline(always)]
+        unsafe fn add_d(a: Self::D, b: Self::D) -> Self::D {
+            (_mm256_add_pd(a.0, b.0), _mm256_add_pd(a.1, b.1))
+        }
+        #[inline(always)]
+        unsafe fn sub_d(a: Self::D, b: Self::D) -> Self::D {
+            (_mm256_sub_pd(a.0, b.0), _mm256_sub_pd(a.1, b.1))
+        }
+        #[inline(always)]
+        unsafe fn mul_d(a: Self::D, b: Self::D) -> Self::D {
+            (_mm256_mul_pd(a.0, b.0), _mm256_mul_pd(a.1, b.1))
+        }
+        #[inline(always)]
+        unsafe fn min_d(a: Self::D, b: Self::D) -> Self::D {
+            (_mm256_min_pd(a.0, b.0), _mm256_min_pd(a.1, b.1))
+        }
+        #[inline(always)]
+        unsafe fn max_d(a: Self::D, b: Self::D) -> Self::D {
+            (_mm256_max_pd(a.0, b.0), _mm256_max_pd(a.1, b.1))
+        }
+        #[inline(always)]
+        unsafe fn to_d(a: __m256i) -> Self::D {
+            let high = _mm256_extracti128_si256(a, 1);
+            (
+                _mm256_cvtepi32_pd(_mm256_castsi256_si128(a)),
+                _mm256_cvtepi32_pd(high),
+            )
+        }
+        #[inline(always)]
+        unsafe fn trunc_d(a: Self::D) -> __m256i {
+            _mm256_set_m128i(_mm256_cvttpd_epi32(a.1), _mm256_cvttpd_epi32(a.0))
+        }
+    }
+
+    /// SSE2, which every x86_64 CPU has.
+    #[derive(Copy, Clone)]
+    pub(in super::super) struct Sse2;
+
+    /// Select `a` where `mask` is set, and `b` elsewhere.
+    #[inline(always)]
+    unsafe fn select(mask: __m128i, a: __m128i, b: __m128i) -> __m128i {
+        _mm_or_si128(_mm_and_si128(mask, a), _mm_andnot_si128(mask, b))
+    }
+
+    /// Multiply 32-bit integers that fit in an i16 (see `Avx2::mul`).
+    #[inline(always)]
+    unsafe fn mul16(a: __m128i, b: __m128i) -> __m128i {
+        _mm_madd_epi16(a, _mm_and_si128(b, _mm_set1_epi32(0xFFFF)))
+    }
+
+    /// Spread 4 3-byte pixels (in the low 12 bytes) out to 32 bits each (SSE2
+    /// can't shuffle bytes).
+    #[inline(always)]
+    unsafe fn spread(v: __m128i) -> __m128i {
+        // 2 pixels in the low 6 bytes of each 64 bits.
+        let v = _mm_unpacklo_epi64(v, _mm_srli_si128(v, 6));
+        let first = _mm_and_si128(v, _mm_set1_epi64x(0x00FF_FFFF));
+        let second = _mm_and_si128(_mm_slli_epi64(v, 8), _mm_set1_epi64x(0x00FF_FFFF << 32));
+        _mm_or_si128(first, second)
+    }
+
+    /// Pack 4 pixels of 32 bits to 3 bytes each (in the low 12 bytes).
+    #[inline(always)]
+    unsafe fn pack(v: __m128i) -> __m128i {
+        let first = _mm_and_si128(v, _mm_set1_epi64x(0x00FF_FFFF));
+        let second = _mm_and_si128(_mm_srli_epi64(v, 8), _mm_set1_epi64x(0xFFFF_FF00_0000));
+        let v = _mm_or_si128(first, second);
+        // Move the high 6 bytes down next to the low 6 bytes.
+        let low = _mm_and_si128(v, _mm_set_epi64x(0, 0xFFFF_FFFF_FFFF));
+        _mm_or_si128(
+            low,
+            _mm_srli_si128(_mm_andnot_si128(_mm_set_epi64x(0, -1), v), 2),
+        )
+    }
+
+    impl Simd for Sse2 {
+        type F = (__m128, __m128);
+        type D = [__m128d; 4];
+        type I = (__m128i, __m128i);
+
+        #[inline(always)]
+        unsafe fn load<const N: usize>(p: &[u8]) -> [Self::I; 3] {
+            let p = &p[..8 * N];
+            let (low, high) = if N == 4 {
+                let low = _mm_loadu_si128(p.as_ptr() as *const __m128i);
+                (low, _mm_loadu_si128(p[16..].as_ptr() as *const __m128i))
+            } else {
+                let low = _mm_loadu_si128(p.as_ptr() as *const __m128i);
+                let high = _mm_loadl_epi64(p[12..].as_ptr() as *const __m128i);
+                let last = _mm_cvtsi32_si128(i32::from_le_bytes([p[20], p[21], p[22], p[23]]));
+                (spread(low), spread(_mm_unpacklo_epi64(high, last)))
+            };
+            let byte = _mm_set1_epi32(0xFF);
+            let mut c = [(low, high); 3];
+            for (i, c) in c.iter_mut().enumerate() {
+                let shift = _mm_cvtsi32_si128(8 * i as i32);
+                c.0 = _mm_and_si128(_mm_srl_epi32(low, shift), byte);
+                c.1 = _mm_and_si128(_mm_srl_epi32(high, shift), byte);
+            }
+            c
+        }
+
+        #[inline(always)]
+        unsafe fn store<const N: usize>(c: [Self::I; 3], p: &mut [u8]) {
+            let p = &mut p[..8 * N];
+            let alpha = _mm_set1_epi32(0xFF00_0000u32 as i32);
+            let low = _mm_or_si128(c[0].0, _mm_slli_epi32(c[1].0, 8));
+            let low = _mm_or_si128(_mm_or_si128(low, _mm_slli_epi32(c[2].0, 16)), alpha);
+            let high = _mm_or_si128(c[0].1, _mm_slli_epi32(c[1].1, 8));
+            let high = _mm_or_si128(_mm_or_si128(high, _mm_slli_epi32(c[2].1, 16)), alpha);
+            if N == 4 {
+                _mm_storeu_si128(p.as_mut_ptr() as *mut __m128i, low);
+                _mm_storeu_si128(p[16..].as_mut_ptr() as *mut __m128i, high);
+            } else {
+                // Store the 12 bytes of the last 4 pixels as 8 and 4.
+                let (low, high) = (pack(low), pack(high));
+                _mm_storeu_si128(p.as_mut_ptr() as *mut __m128i, low);
+                _mm_storel_epi64(p[12..].as_mut_ptr() as *mut __m128i, high);
+                let last = _mm_cvtsi128_si32(_mm_srli_si128(high, 8));
+                p[20..].copy_from_slice(&last.to_le_bytes());
+            }
+        }
+
+        #[inline(always)]
+        unsafe fn splat(a: i32) -> Self::I {
+            (_mm_set1_epi32(a), _mm_set1_epi32(a))
+        }
+        #[inline(always)]
+        unsafe fn add(a: Self::I, b: Self::I) -> Self::I {
+            (_mm_add_epi32(a.0, b.0), _mm_add_epi32(a.1, b.1))
+        }
+        #[inline(always)]
+        unsafe fn sub(a: Self::I, b: Self::I) -> Self::I {
+            (_mm_sub_epi32(a.0, b.0), _mm_sub_epi32(a.1, b.1))
+        }
+        #[inline(always)]
+        unsafe fn mul(a: Self::I, b: Self::I) -> Self::I {
+            (mul16(a.0, b.0), mul16(a.1, b.1))
+        }
+        #[inline(always)]
+        unsafe fn shr(a: Self::I, n: i32) -> Self::I {
+            let n = _mm_cvtsi32_si128(n);
+            (_mm_sra_epi32(a.0, n), _mm_sra_epi32(a.1, n))
+        }
+        #[inline(always)]
+        unsafe fn min(a: Self::I, b: Self::I) -> Self::I {
+            (
+                select(_mm_cmplt_epi32(a.0, b.0), a.0, b.0),
+                select(_mm_cmplt_epi32(a.1, b.1), a.1, b.1),
+            )
+        }
+        #[inline(always)]
+        unsafe fn max(a: Self::I, b: Self::I) -> Self::I {
+            (
+                select(_mm_cmpgt_epi32(a.0, b.0), a.0, b.0),
+                select(_mm_cmpgt_epi32(a.1, b.1), a.1, b.1),
+            )
+        }
+
+        #[inline(always)]
+        unsafe fn splat_f(a: f32) -> Self::F {
+            (_mm_set1_ps(a), _mm_set1_ps(a))
+        }
+        #[inline(always)]
+        unsafe fn add_f(a: Self::F, b: Self::F) -> Self::F {
+            (_mm_add_ps(a.0, b.0), _mm_add_ps(a.1, b.1))
+        }
+        #[inline(always)]
+        unsafe fn sub_f(a: Self::F, b: Self::F) -> Self::F {
+            (_mm_sub_ps(a.0, b.0), _mm_sub_ps(a.1, b.1))
+        }
+        #[inline(always)]
+        unsafe fn mul_f(a: Self::F, b: Self::F) -> Self::F {
+            (_mm_mul_ps(a.0, b.0), _mm_mul_ps(a.1, b.1))
+        }
+        #[inline(always)]
+        unsafe fn div_f(a: Self::F, b: Self::F) -> Self::F {
+            (_mm_div_ps(a.0, b.0), _mm_div_ps(a.1, b.1))
+        }
+        #[inline(always)]
+        unsafe fn min_f(a: Self::F, b: Self::F) -> Self::F {
+            (_mm_min_ps(a.0, b.0), _mm_min_ps(a.1, b.1))
+        }
+        #[inline(always)]
+        unsafe fn max_f(a: Self::F, b: Self::F) -> Self::F {
+            (_mm_max_ps(a.0, b.0), _mm_max_ps(a.1, b.1))
+        }
+        #[inline(always)]
+        unsafe fn to_f(a: Self::I) -> Self::F {
+            (_mm_cvtepi32_ps(a.0), _mm_cvtepi32_ps(a.1))
+        }
+        #[inline(always)]
+        unsafe fn trunc_f(a: Self::F) -> Self::I {
+            (_mm_cvttps_epi32(a.0), _mm_cvttps_epi32(a.1))
+        }
+        #[inline(always)]
+        unsafe fn ge_f(a: Self::F, b: Self::F) -> Self::I {
+            (
+                _mm_castps_si128(_mm_cmpge_ps(a.0, b.0)),
+                _mm_castps_si128(_mm_cmpge_ps(a.1, b.1)),
+            )
+        }
+
+        #[inline(always)]
+        unsafe fn splat_d(a: f64) -> Self::D {
+            [_mm_set1_pd(a); 4]
+        }
+        #[inline(always)]
+        unsafe fn add_d(a: Self::D, b: Self::D) -> Self::D {
+            [
+                _mm_add_pd(a[0], b[0]),
+                _mm_add_pd(a[1], b[1]),
+                _mm_add_pd(a[2], b[2]),
+                _mm_add_pd(a[3], b[3]),
+            ]
+        }
+        #[inline(always)]
+        unsafe fn sub_d(a: Self::D, b: Self::D) -> Self::D {
+            [
+                _mm_sub_pd(a[0], b[0]),
+                _mm_sub_pd(a[1], b[1]),
+                _mm_sub_pd(a[2], b[2]),
+                _mm_sub_pd(a[3], b[3]),
+            ]
+        }
+        #[inline(always)]
+        unsafe fn mul_d(a: Self::D, b: Self::D) -> Self::D {
+            [
+                _mm_mul_pd(a[0], b[0]),
+                _mm_mul_pd(a[1], b[1]),
+                _mm_mul_pd(a[2], b[2]),
+                _mm_mul_pd(a[3], b[3]),
+            ]
+        }
+        #[inline(always)]
+        unsafe fn min_d(a: Self::D, b: Self::D) -> Self::D {
+            [
+                _mm_min_pd(a[0], b[0]),
+                _mm_min_pd(a[1], b[1]),
+                _mm_min_pd(a[2], b[2]),
+                _mm_min_pd(a[3], b[3]),
+            ]
+        }
+        #[inline(always)]
+        unsafe fn max_d(a: Self::D, b: Self::D) -> Self::D {
+            [
+                _mm_max_pd(a[0], b[0]),
+                _mm_max_pd(a[1], b[1]),
+                _mm_max_pd(a[2], b[2]),
+                _mm_max_pd(a[3], b[3]),
+            ]
+        }
+        #[inline(always)]
+        unsafe fn to_d(a: Self::I) -> Self::D {
+            [
+                _mm_cvtepi32_pd(a.0),
+                _mm_cvtepi32_pd(_mm_srli_si128(a.0, 8)),
+                _mm_cvtepi32_pd(a.1),
+                _mm_cvtepi32_pd(_mm_srli_si128(a.1, 8)),
+            ]
+        }
+        #[inline(always)]
+        unsafe fn trunc_d(a: Self::D) -> Self::I {
+            let low = _mm_unpacklo_epi64(_mm_cvttpd_epi32(a[0]), _mm_cvttpd_epi32(a[1]));
+            let high = _mm_unpacklo_epi64(_mm_cvttpd_epi32(a[2]), _mm_cvttpd_epi32(a[3]));
+            (low, high)
+        }
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod arm {
+    use super::Simd;
+    use std::arch::aarch64::*;
+
+    /// NEON, which every aarch64 CPU has.
+    #[derive(Copy, Clone)]
+    pub(in super::super) struct Neon;
+
+    /// Widen 8 bytes to 2 vectors of 4 i32s.
+    #[inline(always)]
+    unsafe fn widen(a: uint8x8_t) -> (int32x4_t, int32x4_t) {
+        let a = vmovl_u8(a);
+        (
+            vreinterpretq_s32_u32(vmovl_u16(vget_low_u16(a))),
+            vreinterpretq_s32_u32(vmovl_high_u16(a)),
+        )
+    }
+
+    /// Narrow 2 vectors of 4 i32s (from 0 to 255) to 8 bytes.
+    #[inline(always)]
+    unsafe fn narrow(a: (int32x4_t, int32x4_t)) -> uint8x8_t {
+        let a = vcombine_s16(vmovn_s32(a.0), vmovn_s32(a.1));
+        vmovn_u16(vreinterpretq_u16_s16(a))
+    }
+
+    impl Simd for Neon {
+        type F = (float32x4_t, float32x4_t);
+        type D = [float64x2_t; 4];
+        type I = (int32x4_t, int32x4_t);
+
+        #[inline(always)]
+        unsafe fn load<const N: usize>(p: &[u8]) -> [Self::I; 3] {
+            let p = &p[..8 * N];
+            if N == 4 {
+                let v = vld4_u8(p.as_ptr());
+                [widen(v.0), widen(v.1), widen(v.2)]
+            } else {
+                let v = vld3_u8(p.as_ptr());
+                [widen(v.0), widen(v.1), widen(v.2)]
+            }
+        }
+
+        #[inline(always)]
+        unsafe fn store<const N: usize>(c: [Self::I; 3], p: &mut [u8]) {
+            let p = &mut p[..8 * N];
+            let [r, g, b] = [narrow(c[0]), narrow(c[1]), narrow(c[2])];
+            if N == 4 {
+                vst4_u8(p.as_mut_ptr(), uint8x8x4_t(r, g, b, vdup_n_u8(255)));
+            } else {
+                vst3_u8(p.as_mut_ptr(), uint8x8x3_t(r, g, b));
+            }
+        }
+
+        #[inline(always)]
+        unsafe fn splat(a: i32) -> Self::I {
+            (vdupq_n_s32(a), vdupq_n_s32(a))
+        }
+        #[inline(always)]
+        unsafe fn add(a: Self::I, b: Self::I) -> Self::I {
+            (vaddq_s32(a.0, b.0), vaddq_s32(a.1, b.1))
+        }
+        #[inline(always)]
+        unsafe fn sub(a: Self::I, b: Self::I) -> Self::I {
+            (vsubq_s32(a.0, b.0), vsubq_s32(a.1, b.1))
+        }
+        #[inline(always)]
+        unsafe fn mul(a: Self::I, b: Self::I) -> Self::I {
+            (vmulq_s32(a.0, b.0), vmulq_s32(a.1, b.1))
+        }
+        #[inline(always)]
+        unsafe fn shr(a: Self::I, n: i32) -> Self::I {
+            // Shifting left by a negative amount shifts right.
+            let n = vdupq_n_s32(-n);
+            (vshlq_s32(a.0, n), vshlq_s32(a.1, n))
+        }
+        #[inline(always)]
+        unsafe fn min(a: Self::I, b: Self::I) -> Self::I {
+            (vminq_s32(a.0, b.0), vminq_s32(a.1, b.1))
+        }
+        #[inline(always)]
+        unsafe fn max(a: Self::I, b: Self::I) -> Self::I {
+            (vmaxq_s32(a.0, b.0), vmaxq_s32(a.1, b.1))
+        }
+
+        #[inline(always)]
+        unsafe fn splat_f(a: f32) -> Self::F {
+            (vdupq_n_f32(a), vdupq_n_f32(a))
+        }
+        #[inline(always)]
+        unsafe fn add_f(a: Self::F, b: Self::F) -> Self::F {
+            (vaddq_f32(a.0, b.0), vaddq_f32(a.1, b.1))
+        }
+        #[inline(always)]
+        unsafe fn sub_f(a: Self::F, b: Self::F) -> Self::F {
+            (vsubq_f32(a.0, b.0), vsubq_f32(a.1, b.1))
+        }
+        #[inline(always)]
+        unsafe fn mul_f(a: Self::F, b: Self::F) -> Self::F {
+            (vmulq_f32(a.0, b.0), vmulq_f32(a.1, b.1))
+        }
+        #[inline(always)]
+        unsafe fn div_f(a: Self::F, b: Self::F) -> Self::F {
+            (vdivq_f32(a.0, b.0), vdivq_f32(a.1, b.1))
+        }
+        #[inline(always)]
+        unsafe fn min_f(a: Self::F, b: Self::F) -> Self::F {
+            (vminq_f32(a.0, b.0), vminq_f32(a.1, b.1))
+        }
+        #[inline(always)]
+        unsafe fn max_f(a: Self::F, b: Self::F) -> Self::F {
+            (vmaxq_f32(a.0, b.0), vmaxq_f32(a.1, b.1))
+        }
+        #[inline(always)]
+        unsafe fn to_f(a: Self::I) -> Self::F {
+            (vcvtq_f32_s32(a.0), vcvtq_f32_s32(a.1))
+        }
+        #[inline(always)]
+        unsafe fn trunc_f(a: Self::F) -> Self::I {
+            (vcvtq_s32_f32(a.0), vcvtq_s32_f32(a.1))
+        }
+        #[inline(always)]
+        unsafe fn ge_f(a: Self::F, b: Self::F) -> Self::I {
+            (
+                vreinterpretq_s32_u32(vcgeq_f32(a.0, b.0)),
+                vreinterpretq_s32_u32(vcgeq_f32(a.1, b.1)),
+            )
+        }
+
+        #[inline(always)]
+        unsafe fn splat_d(a: f64) -> Self::D {
+            [vdupq_n_f64(a); 4]
+        }
+        #[inline(always)]
+        unsafe fn add_d(a: Self::D, b: Self::D) -> Self::D {
+            [
+                vaddq_f64(a[0], b[0]),
+                vaddq_f64(a[1], b[1]),
+                vaddq_f64(a[2], b[2]),
+                vaddq_f64(a[3], b[3]),
+            ]
+        }
+        #[inline(always)]
+        unsafe fn sub_d(a: Self::D, b: Self::D) -> Self::D {
+            [
+                vsubq_f64(a[0], b[0]),
+                vsubq_f64(a[1], b[1]),
+                vsubq_f64(a[2], b[2]),
+                vsubq_f64(a[3], b[3]),
+            ]
+        }
+        #[inline(always)]
+        unsafe fn mul_d(a: Self::D, b: Self::D) -> Self::D {
+            [
+                vmulq_f64(a[0], b[0]),
+                vmulq_f64(a[1], b[1]),
+                vmulq_f64(a[2], b[2]),
+                vmulq_f64(a[3], b[3]),
+            ]
+        }
+        #[inline(always)]
+        unsafe fn min_d(a: Self::D, b: Self::D) -> Self::D {
+            [
+                vminq_f64(a[0], b[0]),
+                vminq_f64(a[1], b[1]),
+                vminq_f64(a[2], b[2]),
+                vminq_f64(a[3], b[3]),
+            ]
+        }
+        #[inline(always)]
+        unsafe fn max_d(a: Self::D, b: Self::D) -> Self::D {
+            [
+                vmaxq_f64(a[0], b[0]),
+                vmaxq_f64(a[1], b[1]),
+                vmaxq_f64(a[2], b[2]),
+                vmaxq_f64(a[3], b[3]),
+            ]
+        }
+        #[inline(always)]
+        unsafe fn to_d(a: Self::I) -> Self::D {
+            [
+                vcvtq_f64_s64(vmovl_s32(vget_low_s32(a.0))),
+                vcvtq_f64_s64(vmovl_high_s32(a.0)),
+                vcvtq_f64_s64(vmovl_s32(vget_low_s32(a.1))),
+                vcvtq_f64_s64(vmovl_high_s32(a.1)),
+            ]
+        }
+        #[inline(always)]
+        unsafe fn trunc_d(a: Self::D) -> Self::I {
+            let i = |a: float64x2_t| vmovn_s64(vcvtq_s64_f64(a));
+            (
+                vcombine_s32(i(a[0]), i(a[1])),
+                vcombine_s32(i(a[2]), i(a[3])),
+            )
+        }
+    }
+}
+
+#[cfg(any(test, not(any(target_arch = "x86_64", target_arch = "aarch64"))))]
+mod scalar {
+    use super::Simd;
+
+    /// Plain arrays, for targets without SIMD (the compiler may still
+    /// vectorize them).
+    #[derive(Copy, Clone)]
+    pub(in super::super) struct Scalar;
+
+    /// Apply `f` to each lane.
+    #[inline(always)]
+    fn lanes<T: Copy, U>(a: [T; 8], f: impl Fn(T) -> U) -> [U; 8] {
+        ::std::array::from_fn(|i| f(a[i]))
+    }
+
+    /// Apply `f` to each pair of lanes.
+    #[inline(always)]
+    fn lanes2<T: Copy>(a: [T; 8], b: [T; 8], f: impl Fn(T, T) -> T) -> [T; 8] {
+        ::std::array::from_fn(|i| f(a[i], b[i]))
+    }
+
+    impl Simd for Scalar {
+        type F = [f32; 8];
+        type D = [f64; 8];
+        type I = [i32; 8];
+
+        #[inline(always)]
+        unsafe fn load<const N: usize>(p: &[u8]) -> [[i32; 8]; 3] {
+            let mut c = [[0; 8]; 3];
+            for (k, p) in p[..8 * N].chunks_exact(N).enumerate() {
+                for (c, p) in c.iter_mut().zip(p) {
+                    c[k] = *p as i32;
+                }
+            }
+            c
+        }
+
+        #[inline(always)]
+        unsafe fn store<const N: usize>(c: [[i32; 8]; 3], p: &mut [u8]) {
+            for (k, p) in p[..8 * N].chunks_exact_mut(N).enumerate() {
+                for (p, c) in p.iter_mut().zip(&c) {
+                    *p = c[k] as u8;
+                }
+                if N == 4 {
+                    p[3] = 255;
+                }
+            }
+        }
+
+        #[inline(always)]
+        unsafe fn splat(a: i32) -> [i32; 8] {
+            [a; 8]
+        }
+        #[inline(always)]
+        unsafe fn add(a: [i32; 8], b: [i32; 8]) -> [i32; 8] {
+            lanes2(a, b, |a, b| a + b)
+        }
+        #[inline(always)]
+        unsafe fn sub(a: [i32; 8], b: [i32; 8]) -> [i32; 8] {
+            lanes2(a, b, |a, b| a - b)
+        }
+        #[inline(always)]
+        unsafe fn mul(a: [i32; 8], b: [i32; 8]) -> [i32; 8] {
+            lanes2(a, b, |a, b| a * b)
+        }
+        #[inline(always)]
+        unsafe fn shr(a: [i32; 8], n: i32) -> [i32; 8] {
+            lanes(a, |a| a >> n)
+        }
+        #[inline(always)]
+        unsafe fn min(a: [i32; 8], b: [i32; 8]) -> [i32; 8] {
+            lanes2(a, b, Ord::min)
+        }
+        #[inline(always)]
+        unsafe fn max(a: [i32; 8], b: [i32; 8]) -> [i32; 8] {
+            lanes2(a, b, Ord::max)
+        }
+
+        #[inline(always)]
+        unsafe fn splat_f(a: f32) -> [f32; 8] {
+            [a; 8]
+        }
+        #[inline(always)]
+        unsafe fn add_f(a: [f32; 8], b: [f32; 8]) -> [f32; 8] {
+            lanes2(a, b, |a, b| a + b)
+        }
+        #[inline(always)]
+        unsafe fn sub_f(a: [f32; 8], b: [f32; 8]) -> [f32; 8] {
+            lanes2(a, b, |a, b| a - b)
+        }
+        #[inline(always)]
+        unsafe fn mul_f(a: [f32; 8], b: [f32; 8]) -> [f32; 8] {
+            lanes2(a, b, |a, b| a * b)
+        }
+        #[inline(always)]
+        unsafe fn div_f(a: [f32; 8], b: [f32; 8]) -> [f32; 8] {
+            lanes2(a, b, |a, b| a / b)
+        }
+        #[inline(always)]
+        unsafe fn min_f(a: [f32; 8], b: [f32; 8]) -> [f32; 8] {
+            lanes2(a, b, f32::min)
+        }
+        #[inline(always)]
+        unsafe fn max_f(a: [f32; 8], b: [f32; 8]) -> [f32; 8] {
+            lanes2(a, b, f32::max)
+        }
+        #[inline(always)]
+        unsafe fn to_f(a: [i32; 8]) -> [f32; 8] {
+            lanes(a, |a| a as f32)
+        }
+        #[inline(always)]
+        unsafe fn trunc_f(a: [f32; 8]) -> [i32; 8] {
+            lanes(a, |a| a as i32)
+        }
+        #[inline(always)]
+        unsafe fn ge_f(a: [f32; 8], b: [f32; 8]) -> [i32; 8] {
+            ::std::array::from_fn(|i| -((a[i] >= b[i]) as i32))
+        }
+
+        #[inline(always)]
+        unsafe fn splat_d(a: f64) -> [f64; 8] {
+            [a; 8]
+        }
+        #[inline(always)]
+        unsafe fn add_d(a: [f64; 8], b: [f64; 8]) -> [f64; 8] {
+            lanes2(a, b, |a, b| a + b)
+        }
+        #[inline(always)]
+        unsafe fn sub_d(a: [f64; 8], b: [f64; 8]) -> [f64; 8] {
+            lanes2(a, b, |a, b| a - b)
+        }
+        #[inline(always)]
+        unsafe fn mul_d(a: [f64; 8], b: [f64; 8]) -> [f64; 8] {
+            lanes2(a, b, |a, b| a * b)
+        }
+        #[inline(always)]
+        unsafe fn min_d(a: [f64; 8], b: [f64; 8]) -> [f64; 8] {
+            lanes2(a, b, f64::min)
+        }
+        #[inline(always)]
+        unsafe fn max_d(a: [f64; 8], b: [f64; 8]) -> [f64; 8] {
+            lanes2(a, b, f64::max)
+        }
+        #[inline(always)]
+        unsafe fn to_d(a: [i32; 8]) -> [f64; 8] {
+            lanes(a, |a| a as f64)
+        }
+        #[inline(always)]
+        unsafe fn trunc_d(a: [f64; 8]) -> [i32; 8] {
+            lanes(a, |a| a as i32)
+        }
+    }
+}
@@ -0,0 +1,140 @@
+// Copyright Jeron Lau 2017 - 2018.
+// Dual-licensed under either the MIT License or the Boost Software License, Version 1.0.
+// (See accompanying file LICENSE_1_0.txt or copy at https://www.boost.org/LICENSE_1_0.txt)
+
+use Depth;
+
+/// The matrix coefficients for converting between RGB and YCbCr.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum YCbCrMatrix {
+    /// ITU-R BT.601 (SD video and JPEG).
+    Bt601,
+    /// ITU-R BT.709 (HD video).
+    Bt709,
+    /// ITU-R BT.2020 non-constant luminance (UHD video).
+    Bt2020,
+}
+
+/// The quantization range of YCbCr samples.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum YCbCrRange {
+    /// Y and Cb/Cr use the whole range, 0-255 for 8 bits (JPEG).
+    Full,
+    /// "Studio" or "TV" range: Y from 16-235 and Cb/Cr from 16-240 for 8
+    /// bits, shifted up for more bits (64-940 and 64-960 for 10 bits, which
+    /// `Depth::U16` stores as 4096-60160 and 4096-61440), most video.
+    Limited,
+}
+
+impl YCbCrRange {
+    /// Get the offset of Y, the scale of Y, the center of Cb/Cr and the
+    /// scale of Cb/Cr for components stored at `depth`.
+    pub(super) fn levels(self, depth: Depth) -> (f32, f32, f32, f32) {
+        // One 8-bit code value; float components use the limit of many bits.
+        let code = match depth {
+            Depth::U8 => 1.0 / 255.0,
+            Depth::U16 => 256.0 / 65535.0,
+            Depth::F16 | Depth::F32 => 1.0 / 256.0,
+        };
+        match self {
+            YCbCrRange::Full => (0.0, 1.0, 0.5, 1.0),
+            YCbCrRange::Limited => (16.0 * code, 219.0 * code, 128.0 * code, 224.0 * code),
+        }
+    }
+}
+
+impl YCbCrMatrix {
+    /// Get the red and blue luma coefficients (Kr, Kb).
+    pub fn kr_kb(self) -> (f32, f32) {
+        match self {
+            YCbCrMatrix::Bt601 => (0.299, 0.114),
+            YCbCrMatrix::Bt709 => (0.2126, 0.0722),
+            YCbCrMatrix::Bt2020 => (0.2627, 0.0593),
+        }
+    }
+
+    /// Convert non-linear (gamma encoded) RGB to YCbCr, all from 0.0 to 1.0
+    /// (Cb and Cr are centered on 0.5 for full range).  Limited range levels
+    /// depend on the `depth` that the components are stored at.
+    pub fn to_ycbcr(self, range: YCbCrRange, depth: Depth, rgb: [f32; 3]) -> [f32; 3] {
+        let (kr, kb) = self.kr_kb();
+        let [r, g, b] = rgb;
+        let y = kr * r + (1.0 - kr - kb) * g + kb * b;
+        let cb = (b - y) / (2.0 * (1.0 - kb));
+        let cr = (r - y) / (2.0 * (1.0 - kr));
+        let (offset, scale, center, c_scale) = range.levels(depth);
+        [
+            offset + scale * y,
+            center + c_scale * cb,
+            center + c_scale * cr,
+        ]
+    }
+
+    /// Convert YCbCr to non-linear (gamma encoded) RGB, all from 0.0 to 1.0
+    /// (see `to_ycbcr`).  Out of range colors aren't clipped.
+    pub fn to_rgb(self, range: YCbCrRange, depth: Depth, ycbcr: [f32; 3]) -> [f32; 3] {
+        let (kr, kb) = self.kr_kb();
+        let (offset, scale, center, c_scale) = range.levels(depth);
+        let [y, cb, cr] = [
+            (ycbcr[0] - offset) / scale,
+            (ycbcr[1] - center) / c_scale,
+            (ycbcr[2] - center) / c_scale,
+        ];
+        let r = y + 2.0 * (1.0 - kr) * cr;
+        let b = y + 2.0 * (1.0 - kb) * cb;
+        let g = (y - kr * r - kb * b) / (1.0 - kr - kb);
+        [r, g, b]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reference_values() {
+        let limited = YCbCrRange::Limited;
+        // 75% color bars in BT.709 limited range (8-bit code values).
+        let bars = [
+            ([0.75, 0.75, 0.75], [180.0, 128.0, 128.0]),
+            ([0.75, 0.75, 0.0], [168.0, 44.0, 136.0]),
+            ([0.0, 0.75, 0.75], [145.0, 147.0, 44.0]),
+            ([0.75, 0.0, 0.0], [51.0, 109.0, 212.0]),
+        ];
+        for &(rgb, code) in &bars {
+            let ycbcr = YCbCrMatrix::Bt709.to_ycbcr(limited, Depth::U8, rgb);
+            for i in 0..3 {
+                assert!((ycbcr[i] * 255.0 - code[i]).abs() < 1.0);
+            }
+        }
+        for &matrix in &[YCbCrMatrix::Bt601, YCbCrMatrix::Bt709, YCbCrMatrix::Bt2020] {
+            for &range in &[YCbCrRange::Full, YCbCrRange::Limited] {
+                for &depth in &[Depth::U8, Depth::U16, Depth::F32] {
+                    let rgb = [0.9, 0.2, 0.4];
+                    let back = matrix.to_rgb(range, depth, matrix.to_ycbcr(range, depth, rgb));
+                    for i in 0..3 {
+                        assert!((back[i] - rgb[i]).abs() < 1e-5);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn limited_levels() {
+        let levels = |depth: Depth, max: f32, rgb| {
+            let ycbcr = YCbCrMatrix::Bt2020.to_ycbcr(YCbCrRange::Limited, depth, rgb);
+            ycbcr.map(|c| (c * max).round())
+        };
+        // 10-bit 64 (black), 940 (white) and 960 (full blue), scaled up.
+        let u16s = |rgb| levels(Depth::U16, 65535.0, rgb);
+        assert_eq!(u16s([0.0; 3]), [4096.0, 32768.0, 32768.0]);
+        assert_eq!(u16s([1.0; 3]), [60160.0, 32768.0, 32768.0]);
+        assert_eq!(u16s([0.0, 0.0, 1.0])[1], 61440.0);
+        let u8s = |rgb| levels(Depth::U8, 255.0, rgb);
+        assert_eq!(u8s([0.0; 3]), [16.0, 128.0, 128.0]);
+        assert_eq!(u8s([1.0; 3]), [235.0, 128.0, 128.0]);
+        let black = YCbCrMatrix::Bt709.to_ycbcr(YCbCrRange::Limited, Depth::F32, [0.0; 3]);
+        assert_eq!(black, [0.0625, 0.5, 0.5]);
+    }
+}